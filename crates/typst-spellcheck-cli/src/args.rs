@@ -1,23 +1,74 @@
 use std::{ops::RangeInclusive, path::PathBuf};
 
-use clap::{arg, command, ArgAction, Parser};
+use clap::{error::ErrorKind, ArgAction, CommandFactory, Parser, ValueEnum};
+use typst_spellcheck::{problem::SortOrder, severity::Severity};
 
-const AFTER_HELP: &str = r#"Host, Port, and Language are required options if not defined in typst-spellcheck.toml.
+const AFTER_HELP: &str = r#"Language and either Base URL or Host and Port are required options if not defined in typst-spellcheck.toml.
 
 Options will override config file values if defined.
 
+Problems are written to stdout, while warnings, statistics and logs are written to stderr.
+
+Severity is one of "error", "warning", "info" or "hint". The CLI exits with code 1 if a
+problem is at least as severe as --fail-on, and with code 2 if the check fails.
+
+Premium credentials are read from the LANGUAGETOOL_USERNAME and LANGUAGETOOL_API_KEY
+environment variables if they are not defined in typst-spellcheck.toml.
+
 typst-spellcheck.toml:
 | host      String
 | port      u16
+| base_url  Option<String>
 | language  String
 | picky     Option<bool>
 |
+| username  Option<String>
+| api_key   Option<String>
+|
 | disabled_rules        Option<Vec<String>>
 | disabled_categories   Option<Vec<String>>
 | ignore_words          Option<Vec<String>>
+|
+| check_document_metadata   Option<bool>
+| check_bibliography        Option<bool>
+| out_of_flow_functions     Option<Vec<String>>
+| skip_functions            Option<Vec<String>>
+| skip_labels               Option<Vec<String>>
+|
+| [chunking]
+| min_length            Option<usize>
+| max_length            Option<usize>
+| merge_across_headings Option<bool>
+| separate_blocks       Option<bool>
+|
+| [headings], [list_items], [terms], [table_cells], [captions], [footnotes]
+| disabled_rules        Option<Vec<String>>
+| disabled_categories   Option<Vec<String>>
+|
+| [raw]
+| prose_languages       Option<Vec<String>>
+| check_comments        Option<bool>
+|
+| [code_content]
+| let_bindings          Option<bool>
+| show_rules            Option<bool>
+| function_args         Option<bool>
+|
+| [severity]
+| rules                 Option<Map<String, Severity>>
+| categories            Option<Map<String, Severity>>
+|
+| [placeholders]
+| reference               Option<String>
+| reference_supplements   Option<Map<String, String>>
+| citation                Option<String>
+| inline_equation         Option<String>
+| block_equation          Option<String>
+| raw                     Option<String>
+| field_access            Option<String>
 "#;
 
-/// Spellcheck a typst file with a selfhosted languagetool server.
+/// Spellcheck a typst file with a selfhosted or public languagetool server.
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None, after_help = Some(AFTER_HELP))]
 pub struct Args {
@@ -29,6 +80,14 @@ pub struct Args {
     #[arg(short, long, value_parser = port_in_range)]
     pub port: Option<u16>,
 
+    /// Full languagetool API URL, overrides host and port (e.g. https://api.languagetoolplus.com/v2)
+    #[arg(long)]
+    pub base_url: Option<String>,
+
+    /// Languagetool premium username
+    #[arg(long)]
+    pub username: Option<String>,
+
     /// Language for spellcheck
     #[arg(short, long)]
     pub language: Option<String>,
@@ -45,6 +104,15 @@ pub struct Args {
     #[arg(long)]
     pub ignore_words: Option<Vec<String>>,
 
+    /// Merge paragraphs shorter than this many bytes (defaults to 512)
+    #[arg(long)]
+    pub min_chunk_length: Option<usize>,
+
+    /// Split paragraphs longer than this many bytes (defaults to the text limit of the public
+    /// languagetool API, and to no limit for self-hosted servers)
+    #[arg(long)]
+    pub max_chunk_length: Option<usize>,
+
     /// Custom path to config file (checks pwd by default)
     #[arg(long, value_hint = clap::ValueHint::DirPath)]
     pub config_file: Option<PathBuf>,
@@ -53,6 +121,14 @@ pub struct Args {
     #[arg(long, action = ArgAction::SetTrue)]
     pub picky: Option<bool>,
 
+    /// Check the title and keywords in `#set document(..)`
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub check_document_metadata: Option<bool>,
+
+    /// Check the entry titles of bibliography files
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub check_bibliography: Option<bool>,
+
     /// Enable debug file output
     #[arg(long, action = ArgAction::SetTrue)]
     pub debug: Option<bool>,
@@ -61,11 +137,143 @@ pub struct Args {
     #[arg(long, action = ArgAction::SetTrue)]
     pub no_default_disabled_rules: Option<bool>,
 
+    /// Fail if a problem couldn't be mapped to its exact position in the file
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub strict_mapping: Option<bool>,
+
+    /// Print timings, request latency and problem counts after the problems, only with
+    /// `--format text` since the JSON output always includes them
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub stats: Option<bool>,
+
+    /// Order to print problems in
+    #[arg(long, value_enum, default_value_t = SortBy::Position)]
+    pub sort: SortBy,
+
+    /// Exit with code 1 if there are problems at least this severe
+    #[arg(long, value_enum, default_value_t = FailOn::Never)]
+    pub fail_on: FailOn,
+
+    /// Output format, `short` prints a `file:line:column: rule: message` line per problem
+    /// and `json` prints problems and statistics as a single JSON object
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// When to color the output, `auto` colors it for terminals unless NO_COLOR is set
+    #[arg(long, value_enum, default_value_t = ColorWhen::Auto)]
+    pub color: ColorWhen,
+
+    /// Group the problems of the text output with a line per problem, only with
+    /// `--format text`
+    #[arg(long, value_enum)]
+    pub group_by: Option<GroupBy>,
+
+    /// Print the most common misspelled words after the problems, only with `--format text`
+    #[arg(long, action = ArgAction::SetTrue)]
+    pub summary: Option<bool>,
+
     /// Typst file to spellcheck
     #[arg(value_hint = clap::ValueHint::DirPath)]
     pub file: PathBuf,
 }
 
+impl Args {
+    /// Rejects the options of the text output when another output format is used.
+    pub fn check_format_options(&self) -> Result<(), clap::Error> {
+        if self.format == OutputFormat::Text {
+            return Ok(());
+        }
+
+        let option = if self.group_by.is_some() {
+            "--group-by"
+        } else if self.summary.unwrap_or(false) {
+            "--summary"
+        } else if self.stats.unwrap_or(false) {
+            "--stats"
+        } else {
+            return Ok(());
+        };
+
+        Err(Args::command().error(
+            ErrorKind::ArgumentConflict,
+            format!("the argument '{option}' can only be used with '--format text'"),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Short,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorWhen {
+    /// Sets when the stdout and stderr streams of `anstream` are colored.
+    pub fn write_global(self) {
+        let choice = match self {
+            ColorWhen::Auto => anstream::ColorChoice::Auto,
+            ColorWhen::Always => anstream::ColorChoice::Always,
+            ColorWhen::Never => anstream::ColorChoice::Never,
+        };
+
+        choice.write_global();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    Rule,
+    Category,
+    Word,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortBy {
+    Position,
+    Rule,
+    Severity,
+}
+
+impl From<SortBy> for SortOrder {
+    fn from(sort_by: SortBy) -> Self {
+        match sort_by {
+            SortBy::Position => SortOrder::Position,
+            SortBy::Rule => SortOrder::Rule,
+            SortBy::Severity => SortOrder::Severity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FailOn {
+    Never,
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl FailOn {
+    /// The least severe severity that fails the check.
+    pub fn threshold(self) -> Option<Severity> {
+        match self {
+            FailOn::Never => None,
+            FailOn::Error => Some(Severity::Error),
+            FailOn::Warning => Some(Severity::Warning),
+            FailOn::Info => Some(Severity::Info),
+            FailOn::Hint => Some(Severity::Hint),
+        }
+    }
+}
+
 const PORT_RANGE: RangeInclusive<usize> = 1..=65535;
 fn port_in_range(s: &str) -> Result<u16, String> {
     let port: usize = s
@@ -81,3 +289,50 @@ fn port_in_range(s: &str) -> Result<u16, String> {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::{error::ErrorKind, Parser};
+
+    use super::Args;
+
+    fn check(args: &[&str]) -> Result<(), ErrorKind> {
+        let args =
+            Args::try_parse_from(["typst-spellcheck-cli", "doc.typ"].iter().chain(args)).unwrap();
+
+        args.check_format_options().map_err(|error| error.kind())
+    }
+
+    #[test]
+    fn text_options_need_text_format() {
+        assert_eq!(
+            check(&["--group-by", "rule", "--summary", "--stats"]),
+            Ok(())
+        );
+        assert_eq!(check(&["--format", "text", "--summary"]), Ok(()));
+        assert_eq!(check(&["--format", "json"]), Ok(()));
+
+        assert_eq!(
+            check(&["--format", "short", "--group-by", "word"]),
+            Err(ErrorKind::ArgumentConflict)
+        );
+        assert_eq!(
+            check(&["--format", "json", "--summary"]),
+            Err(ErrorKind::ArgumentConflict)
+        );
+    }
+
+    #[test]
+    fn stats_need_text_format() {
+        assert_eq!(check(&["--format", "text", "--stats"]), Ok(()));
+
+        assert_eq!(
+            check(&["--format", "short", "--stats"]),
+            Err(ErrorKind::ArgumentConflict)
+        );
+        assert_eq!(
+            check(&["--format", "json", "--stats"]),
+            Err(ErrorKind::ArgumentConflict)
+        );
+    }
+}
@@ -2,7 +2,7 @@ use std::{env, fs};
 
 use crate::Error;
 use serde::{Deserialize, Serialize};
-use typst_spellcheck::{LanguageToolConfig, SpellcheckConfig};
+use typst_spellcheck::{ChunkingConfig, LanguageToolConfig, SpellcheckConfig};
 
 use crate::args::Args;
 
@@ -16,6 +16,9 @@ pub struct Config {
 
 const DEFAULT_DISABLED_RULES: [&str; 1] = ["WHITESPACE_RULE"];
 
+const USERNAME_ENV: &str = "LANGUAGETOOL_USERNAME";
+const API_KEY_ENV: &str = "LANGUAGETOOL_API_KEY";
+
 impl Config {
     pub fn from_args_or_file(args: Args) -> Result<Config, Error> {
         let config_path = match args.config_file {
@@ -31,11 +34,6 @@ impl Config {
             let config: Config = toml::from_str(&contents)?;
             config
         } else {
-            // if no config file, then make sure host, port, and language are defined.
-            if args.host.is_none() || args.port.is_none() || args.language.is_none() {
-                return Err(Error::RequiredOptions);
-            }
-
             Config::default()
         };
 
@@ -62,9 +60,18 @@ impl Config {
         let languagetool_config = LanguageToolConfig {
             host: args.host.unwrap_or(config_file.languagetool_config.host),
             port: args.port.unwrap_or(config_file.languagetool_config.port),
+            base_url: args.base_url.or(config_file.languagetool_config.base_url),
             language: args
                 .language
                 .unwrap_or(config_file.languagetool_config.language),
+            username: args
+                .username
+                .or(config_file.languagetool_config.username)
+                .or_else(|| env::var(USERNAME_ENV).ok()),
+            api_key: config_file
+                .languagetool_config
+                .api_key
+                .or_else(|| env::var(API_KEY_ENV).ok()),
 
             disabled_categories: args
                 .disabed_categories
@@ -73,10 +80,36 @@ impl Config {
             picky: args.picky.or(config_file.languagetool_config.picky),
         };
 
+        // Make sure there is a server to send requests to and a language to check with.
+        let has_server = languagetool_config.base_url.is_some()
+            || (!languagetool_config.host.is_empty() && languagetool_config.port != 0);
+        if !has_server || languagetool_config.language.is_empty() {
+            return Err(Error::RequiredOptions);
+        }
+
         let spellcheck_config = SpellcheckConfig {
             ignore_words: args
                 .ignore_words
                 .or(config_file.spellcheck_config.ignore_words),
+            chunking: ChunkingConfig {
+                min_length: args
+                    .min_chunk_length
+                    .or(config_file.spellcheck_config.chunking.min_length),
+                max_length: args
+                    .max_chunk_length
+                    .or(config_file.spellcheck_config.chunking.max_length),
+                ..config_file.spellcheck_config.chunking
+            },
+            placeholders: config_file.spellcheck_config.placeholders,
+            check_document_metadata: args
+                .check_document_metadata
+                .filter(|enabled| *enabled)
+                .or(config_file.spellcheck_config.check_document_metadata),
+            check_bibliography: args
+                .check_bibliography
+                .filter(|enabled| *enabled)
+                .or(config_file.spellcheck_config.check_bibliography),
+            ..config_file.spellcheck_config
         };
 
         Ok(Config {
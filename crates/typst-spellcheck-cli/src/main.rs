@@ -5,31 +5,74 @@ mod output;
 
 use std::{
     fs::{self},
-    io::{self},
+    io::{self, IsTerminal, Write},
+    process::ExitCode,
 };
 
-use args::Args;
+use args::{Args, OutputFormat};
 use clap::Parser;
 use config_file::Config;
 use logger::init_fern;
-use output::display_problems;
+use output::{display_json, display_problems, display_short, DisplayOptions};
 use owo_colors::OwoColorize;
+use terminal_size::Width;
 use thiserror::Error;
-use typst_spellcheck::spellchecker::{check_file, Spellchecker};
+use typst_spellcheck::{
+    problem::sort_problems,
+    spellchecker::{check_file, check_source::CheckOptions, Spellchecker},
+};
+
+/// Exit code when there are problems at least as severe as `--fail-on`.
+const PROBLEMS_EXIT_CODE: u8 = 1;
+/// Exit code when the check itself fails.
+const ERROR_EXIT_CODE: u8 = 2;
 
 #[tokio::main]
-async fn main() {
-    if let Err(e) = run().await {
-        eprintln!("{}: {}", "Error".red().bold(), e);
+async fn main() -> ExitCode {
+    exit_code(run().await)
+}
+
+/// Gets the exit code for the result of [`run`], and writes errors to stderr.
+fn exit_code(result: Result<bool, Error>) -> ExitCode {
+    match result {
+        Ok(true) => ExitCode::from(PROBLEMS_EXIT_CODE),
+        Ok(false) => ExitCode::SUCCESS,
+        // The output was piped into a program that exited early, e.g. `head`
+        Err(Error::Display(e)) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(e) => {
+            // Written through anstream so `--color` and NO_COLOR apply
+            let _ = writeln!(anstream::stderr(), "{}: {}", "Error".red().bold(), e);
+            ExitCode::from(ERROR_EXIT_CODE)
+        }
     }
 }
 
-async fn run() -> Result<(), Error> {
+/// Checks the file and displays the problems, returning whether the problems fail the
+/// check.
+async fn run() -> Result<bool, Error> {
     let args = Args::parse();
+    args.check_format_options()
+        .unwrap_or_else(|error| error.exit());
+    args.color.write_global();
     let debug = args.debug.unwrap_or(false);
     init_fern(debug)?;
 
     let file = args.file.clone();
+    let display_options = DisplayOptions {
+        stats: args.stats.unwrap_or(false),
+        group_by: args.group_by,
+        summary: args.summary.unwrap_or(false),
+        // Only wrap when writing to a terminal
+        width: io::stdout()
+            .is_terminal()
+            .then(terminal_size::terminal_size)
+            .flatten()
+            .map(|(Width(width), _)| width as usize),
+    };
+    let strict_mapping = args.strict_mapping.unwrap_or(false);
+    let format = args.format;
+    let sort = args.sort;
+    let fail_on = args.fail_on;
     let config = Config::from_args_or_file(args)?;
 
     // check if defined file exists
@@ -40,12 +83,50 @@ async fn run() -> Result<(), Error> {
     let spellchecker = Spellchecker::new(config.languagetool_config, config.spellcheck_config);
 
     let contents = fs::read_to_string(&file).map_err(Error::ReadFile)?;
-    let (mut problems, metadata) = spellchecker
-        .check_file(&file.to_string_lossy(), contents, debug)
+    let (mut problems, failed_paragraphs, metadata) = spellchecker
+        .check_file(
+            &file.to_string_lossy(),
+            contents,
+            &CheckOptions::new().with_debug(debug),
+        )
         .await?;
-    problems.sort();
+    sort_problems(&mut problems, sort.into());
+
+    let unlocated_count = metadata.unlocated_count;
+    let failed = fail_on
+        .threshold()
+        .is_some_and(|threshold| problems.iter().any(|problem| problem.severity >= threshold));
+
+    match format {
+        OutputFormat::Text => display_problems(
+            &file.to_string_lossy(),
+            problems,
+            failed_paragraphs,
+            metadata,
+            display_options,
+        )
+        .map_err(Error::Display)?,
+        OutputFormat::Short => display_short(&file.to_string_lossy(), problems, failed_paragraphs)
+            .map_err(Error::Display)?,
+        OutputFormat::Json => display_json(
+            &file.to_string_lossy(),
+            problems,
+            failed_paragraphs,
+            metadata,
+        )
+        .map_err(Error::Display)?,
+    }
+
+    check_mapping(strict_mapping, unlocated_count)?;
+
+    Ok(failed)
+}
 
-    display_problems(&file.to_string_lossy(), problems, metadata).map_err(Error::Display)?;
+/// Fails with `--strict-mapping` if some problems are only located at their paragraph.
+fn check_mapping(strict_mapping: bool, unlocated_count: usize) -> Result<(), Error> {
+    if strict_mapping && unlocated_count > 0 {
+        return Err(Error::UnlocatedProblems(unlocated_count));
+    }
 
     Ok(())
 }
@@ -64,7 +145,7 @@ pub enum Error {
     #[error("Failed to parse config file.\n{0}")]
     ParseConfig(#[from] toml::de::Error),
 
-    #[error("Without a config file the 'host', 'port', and 'language' options are required.")]
+    #[error("The 'language' option and either the 'base_url' or the 'host' and 'port' options are required.")]
     RequiredOptions,
 
     #[error("Specified file does not exist.")]
@@ -78,4 +159,25 @@ pub enum Error {
 
     #[error("Failed to display problems.\n{0}")]
     Display(#[source] io::Error),
+
+    #[error("{0} problem(s) could not be mapped to their exact position.")]
+    UnlocatedProblems(usize),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::ExitCode;
+
+    use super::{check_mapping, exit_code, ERROR_EXIT_CODE};
+
+    #[test]
+    fn strict_mapping_fails_on_unlocated_problems() {
+        let exit = |strict_mapping, unlocated_count| {
+            exit_code(check_mapping(strict_mapping, unlocated_count).map(|()| false))
+        };
+
+        assert_eq!(exit(true, 1), ExitCode::from(ERROR_EXIT_CODE));
+        assert_eq!(exit(true, 0), ExitCode::SUCCESS);
+        assert_eq!(exit(false, 1), ExitCode::SUCCESS);
+    }
 }
@@ -1,81 +1,652 @@
-use std::io::{stderr, BufWriter, Write};
+use std::{
+    io::{stderr, stdout, BufWriter, Write},
+    time::Duration,
+};
 
 use owo_colors::{OwoColorize, Style};
-use typst_spellcheck::{problem::Problem, spellchecker::metadata::Metadata};
+use serde::Serialize;
+use typst_spellcheck::{
+    problem::Problem,
+    range::Range,
+    severity::Severity,
+    spellchecker::{failed_paragraph::FailedParagraph, metadata::Metadata},
+};
+
+use crate::args::GroupBy;
+
+/// What the text output shows besides the problems.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisplayOptions {
+    /// Show a table of timings, request statistics and problem counts.
+    pub stats: bool,
+    /// Show the problems in groups with a line per problem, instead of a block each.
+    pub group_by: Option<GroupBy>,
+    /// Show the most common misspelled words.
+    pub summary: bool,
+    /// Wrap messages to this many columns.
+    pub width: Option<usize>,
+}
 
 pub fn display_problems(
     file_path: &str,
     problems: Vec<Problem>,
+    failed_paragraphs: Vec<FailedParagraph>,
     metadata: Metadata,
+    options: DisplayOptions,
 ) -> Result<(), std::io::Error> {
-    let handle = stderr().lock();
-    let mut writer = BufWriter::new(handle);
+    // Problems are the output, everything else is a diagnostic
+    let mut out = BufWriter::new(anstream::stdout().lock());
+    let mut writer = BufWriter::new(anstream::stderr().lock());
 
-    let emph = Style::new().yellow().bold();
     let sub = Style::new().bright_black().bold();
 
-    for problem in problems.iter() {
-        let title_sub = format!("{}: `{}`", problem.short_message, problem.match_string);
+    match options.group_by {
+        Some(group_by) => display_groups(&mut out, file_path, &problems, group_by, options.width)?,
+        None => {
+            for problem in problems.iter() {
+                display_problem(&mut out, file_path, problem, options.width)?;
+            }
+        }
+    }
+    out.flush()?;
+
+    for failed_paragraph in failed_paragraphs.iter() {
         writeln!(
             &mut writer,
             "{}{} {}",
-            "Problem".style(emph),
+            "Unchecked".red().bold(),
             ":".style(sub),
-            title_sub.style(sub)
+            "paragraph could not be checked".style(sub)
         )?;
 
-        writeln!(&mut writer, "{}, {}", file_path, problem.range)?;
+        let paragraph_file = failed_paragraph.file.as_deref().unwrap_or(file_path);
+        match failed_paragraph.range {
+            Some(range) => writeln!(&mut writer, "{}, {}", paragraph_file, range)?,
+            None => writeln!(&mut writer, "{}", paragraph_file)?,
+        }
 
         writeln!(&mut writer, "   |")?;
-        writeln!(&mut writer, "   | {}", problem.context.as_str())?;
+        for line in failed_paragraph.error.to_string().lines() {
+            writeln!(&mut writer, "   | {}", line)?;
+        }
         writeln!(&mut writer, "   |")?;
 
+        writeln!(&mut writer)?;
+    }
+
+    if !failed_paragraphs.is_empty() {
         writeln!(
             &mut writer,
-            "   {} {}: {}",
-            "=".bold(),
-            "Detail".style(sub),
-            problem.message
+            "{}: {} paragraph(s) could not be checked",
+            "Warning".yellow().bold(),
+            failed_paragraphs.len().bold()
         )?;
+    }
+
+    if metadata.unlocated_count > 0 {
         writeln!(
             &mut writer,
-            "   {} {}: {}",
-            "=".bold(),
-            "Category".style(sub),
-            problem.rule_category
+            "{}: {} problem(s) could not be mapped to their exact position",
+            "Warning".yellow().bold(),
+            metadata.unlocated_count.bold()
         )?;
+    }
+
+    writeln!(
+        &mut writer,
+        "{}: processed {} chunks ({} words) and found {} problem(s) in {:.2}s",
+        "Finished".green().bold(),
+        metadata.paragraph_count.bold(),
+        metadata.word_count,
+        problems.len().bold(),
+        metadata.languagetool_request_time.as_secs_f32().bold()
+    )?;
+
+    writer.flush()?;
+
+    if options.summary {
+        writeln!(&mut out)?;
+        display_summary(&mut out, &problems)?;
+        out.flush()?;
+    }
+
+    if options.stats {
+        writeln!(&mut writer)?;
+        display_stats(&mut writer, &metadata)?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writes a problem as a block with its context, details and replacements.
+fn display_problem(
+    writer: &mut impl Write,
+    file_path: &str,
+    problem: &Problem,
+    width: Option<usize>,
+) -> Result<(), std::io::Error> {
+    let emph = Style::new().yellow().bold();
+    let sub = Style::new().bright_black().bold();
+
+    let title_sub = format!("{}: `{}`", problem.short_message, problem.match_string);
+    writeln!(
+        writer,
+        "{}{} {}",
+        severity_label(problem.severity).style(severity_style(problem.severity)),
+        ":".style(sub),
+        title_sub.style(sub)
+    )?;
+
+    let problem_file = problem.file.as_deref().unwrap_or(file_path);
+    if problem.unlocated {
         writeln!(
-            &mut writer,
+            writer,
+            "{}, {} {}",
+            problem_file,
+            problem.range,
+            "(paragraph, exact position unknown)".style(sub)
+        )?;
+    } else {
+        writeln!(writer, "{}, {}", problem_file, problem.range)?;
+    }
+
+    writeln!(writer, "   |")?;
+    writeln!(writer, "   | {}", problem.context.as_str())?;
+    writeln!(writer, "   |")?;
+
+    // The message starts after the label, so its first line is shorter
+    let label = "Detail";
+    let label_width = format!("   = {label}: ").chars().count();
+    writeln!(
+        writer,
+        "   {} {}: {}",
+        "=".bold(),
+        label.style(sub),
+        wrap(&problem.message, width, label_width, DETAIL_INDENT)
+    )?;
+    writeln!(
+        writer,
+        "   {} {}: {}",
+        "=".bold(),
+        "Category".style(sub),
+        problem.rule_category
+    )?;
+    writeln!(
+        writer,
+        "   {} {}: {}",
+        "=".bold(),
+        "Rule ID".style(sub),
+        problem.rule_id
+    )?;
+    if let Some(url) = problem.rule_urls.first() {
+        writeln!(
+            writer,
             "   {} {}: {}",
             "=".bold(),
-            "Rule ID".style(sub),
-            problem.rule_id
+            "More info".style(sub),
+            url
         )?;
+    }
 
-        if !problem.replacements.is_empty() {
-            writeln!(&mut writer, "{}:", "Did you mean".style(emph))?;
+    if !problem.replacements.is_empty() {
+        writeln!(writer, "{}:", "Did you mean".style(emph))?;
 
-            for (index, replacement) in problem.replacements.iter().enumerate() {
-                let number = format!("{}.", index + 1);
+        for (index, replacement) in problem.replacements.iter().enumerate() {
+            let number = format!("{}.", index + 1);
 
-                writeln!(&mut writer, "   {} {}", number.style(sub), replacement)?;
-            }
+            writeln!(writer, "   {} {}", number.style(sub), replacement)?;
         }
+    }
 
-        writeln!(&mut writer)?;
+    writeln!(writer)?;
+
+    Ok(())
+}
+
+/// Writes the problems in groups that share a rule, category or word, largest first.
+fn display_groups(
+    writer: &mut impl Write,
+    file_path: &str,
+    problems: &[Problem],
+    group_by: GroupBy,
+    width: Option<usize>,
+) -> Result<(), std::io::Error> {
+    let sub = Style::new().bright_black().bold();
+
+    let groups = group(problems.iter(), |problem| match group_by {
+        GroupBy::Rule => &problem.rule_id,
+        GroupBy::Category => &problem.rule_category,
+        GroupBy::Word => &problem.match_string,
+    });
+
+    for (key, group) in groups {
+        writeln!(
+            writer,
+            "{} {}",
+            key.bold(),
+            format!("({} problem(s))", group.len()).style(sub)
+        )?;
+
+        for problem in group {
+            let location = format!(
+                "   {}, {}:",
+                problem.file.as_deref().unwrap_or(file_path),
+                problem.range
+            );
+            let match_string = format!("`{}`", problem.match_string);
+            let column = location.chars().count() + match_string.chars().count() + 2;
+
+            writeln!(
+                writer,
+                "{} {} {}",
+                location,
+                match_string.style(severity_style(problem.severity)),
+                wrap(&problem.message, width, column, DETAIL_INDENT)
+            )?;
+        }
+
+        writeln!(writer)?;
     }
 
+    Ok(())
+}
+
+/// Indentation of the wrapped lines of messages.
+const DETAIL_INDENT: usize = 5;
+
+/// Wraps text to `width` columns, where its first line starts at `first_column` and the
+/// other lines are indented by `indent` spaces. Words longer than a line aren't broken.
+fn wrap(text: &str, width: Option<usize>, first_column: usize, indent: usize) -> String {
+    let Some(width) = width else {
+        return text.to_string();
+    };
+
+    let mut wrapped = String::new();
+    let mut column = first_column;
+    let mut line_is_empty = true;
+
+    for word in text.split_whitespace() {
+        let word_width = word.chars().count();
+
+        if !line_is_empty && column + 1 + word_width > width {
+            wrapped.push('\n');
+            wrapped.push_str(&" ".repeat(indent));
+            column = indent;
+            line_is_empty = true;
+        }
+
+        if !line_is_empty {
+            wrapped.push(' ');
+            column += 1;
+        }
+        wrapped.push_str(word);
+        column += word_width;
+        line_is_empty = false;
+    }
+
+    wrapped
+}
+
+/// Number of words listed by `--summary`.
+const SUMMARY_WORD_COUNT: usize = 20;
+
+/// Writes the most common misspelled words, along with a config line that ignores them.
+fn display_summary(writer: &mut impl Write, problems: &[Problem]) -> Result<(), std::io::Error> {
+    let sub = Style::new().bright_black().bold();
+
+    let misspellings = problems.iter().filter(|problem| {
+        problem.issue_type.as_deref() == Some("misspelling") || problem.rule_category == "TYPOS"
+    });
+    let words = group(misspellings, |problem| &problem.match_string);
+
     writeln!(
-        &mut writer,
-        "{}: processed {} chunks ({} words) and found {} problem(s) in {:.2}s",
-        "Finished".green().bold(),
-        metadata.paragraph_count.bold(),
-        metadata.word_count,
-        problems.len().bold(),
-        metadata.languagetool_request_time.as_secs_f32().bold()
+        writer,
+        "{}: {} different misspelled word(s)",
+        "Summary".green().bold(),
+        words.len().bold()
     )?;
+    if words.is_empty() {
+        return Ok(());
+    }
+
+    let top_words = &words[..words.len().min(SUMMARY_WORD_COUNT)];
+    let width = top_words[0].1.len().to_string().len();
+    for (word, group) in top_words {
+        writeln!(
+            writer,
+            "   {} {}",
+            format!("{:>width$}", group.len()).style(sub),
+            word
+        )?;
+    }
+
+    let quoted: Vec<String> = top_words
+        .iter()
+        .map(|(word, _)| toml::Value::String(word.to_string()).to_string())
+        .collect();
+    writeln!(writer, "   ignore_words = [{}]", quoted.join(", "))?;
 
+    Ok(())
+}
+
+/// Groups problems by a key, sorted by the size of the group and then by the key.
+fn group<'a>(
+    problems: impl Iterator<Item = &'a Problem>,
+    key: impl Fn(&'a Problem) -> &'a String,
+) -> Vec<(&'a str, Vec<&'a Problem>)> {
+    let mut groups: Vec<(&str, Vec<&Problem>)> = vec![];
+
+    for problem in problems {
+        let key = key(problem).as_str();
+
+        match groups.iter_mut().find(|(group_key, _)| *group_key == key) {
+            Some((_, group)) => group.push(problem),
+            None => groups.push((key, vec![problem])),
+        }
+    }
+
+    groups.sort_by(|(a_key, a), (b_key, b)| b.len().cmp(&a.len()).then_with(|| a_key.cmp(b_key)));
+
+    groups
+}
+
+fn severity_label(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "Error",
+        Severity::Warning => "Warning",
+        Severity::Info => "Info",
+        Severity::Hint => "Hint",
+    }
+}
+
+fn severity_style(severity: Severity) -> Style {
+    match severity {
+        Severity::Error => Style::new().red().bold(),
+        Severity::Warning => Style::new().yellow().bold(),
+        Severity::Info => Style::new().blue().bold(),
+        Severity::Hint => Style::new().cyan().bold(),
+    }
+}
+
+/// Writes the timings, request statistics and problem counts of a check as a table.
+fn display_stats(writer: &mut impl Write, metadata: &Metadata) -> Result<(), std::io::Error> {
+    let sub = Style::new().bright_black().bold();
+    let seconds = |duration: Duration| format!("{:.3}s", duration.as_secs_f64());
+
+    let timings = &metadata.timings;
+    let requests = &metadata.request_statistics;
+    let dropped = &metadata.dropped_matches;
+    let chunks = &metadata.chunk_statistics;
+
+    let mut rows = vec![
+        ("Parse", seconds(timings.parse)),
+        ("Preprocess", seconds(timings.preprocess)),
+        ("Request", seconds(timings.request)),
+        ("Mapping", seconds(timings.mapping)),
+        ("Requests", requests.request_count.to_string()),
+        ("Average latency", seconds(requests.average_latency())),
+        (
+            "Chunks",
+            format!(
+                "{} from {} paragraphs, {}-{} bytes (mean {})",
+                chunks.chunk_count,
+                chunks.source_paragraph_count,
+                chunks.min_length,
+                chunks.max_length,
+                chunks.mean_length
+            ),
+        ),
+        (
+            "Dropped matches",
+            format!(
+                "{} ({} ignored words, {} unmapped, {} duplicates)",
+                dropped.total(),
+                dropped.ignored_words,
+                dropped.unmapped,
+                dropped.duplicates
+            ),
+        ),
+        ("Unlocated", metadata.unlocated_count.to_string()),
+    ];
+    let counts = &metadata.problem_counts;
+    rows.extend(
+        counts
+            .by_severity
+            .iter()
+            .rev()
+            .map(|(severity, count)| ("Severity", format!("{severity}: {count}"))),
+    );
+    rows.extend(
+        counts
+            .by_category
+            .iter()
+            .map(|(category, count)| ("Category", format!("{category}: {count}"))),
+    );
+    rows.extend(
+        counts
+            .by_rule
+            .iter()
+            .map(|(rule, count)| ("Rule", format!("{rule}: {count}"))),
+    );
+
+    let width = rows.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+
+    writeln!(writer, "{}:", "Statistics".green().bold())?;
+    for (name, value) in rows {
+        writeln!(
+            writer,
+            "   {} {}",
+            format!("{name:width$}").style(sub),
+            value
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes one line per problem to stdout, in the `file:line:column: rule: message` format
+/// that editors read into quickfix lists. Failed paragraphs are written to stderr.
+pub fn display_short(
+    file_path: &str,
+    problems: Vec<Problem>,
+    failed_paragraphs: Vec<FailedParagraph>,
+) -> Result<(), std::io::Error> {
+    let mut writer = BufWriter::new(stdout().lock());
+    for problem in problems.iter() {
+        writeln!(
+            &mut writer,
+            "{}:{}:{}: {}: {}",
+            problem.file.as_deref().unwrap_or(file_path),
+            problem.range.start.line,
+            problem.range.start.column,
+            problem.rule_id,
+            single_line(&problem.message)
+        )?;
+    }
+    writer.flush()?;
+
+    let mut writer = BufWriter::new(stderr().lock());
+    for failed_paragraph in failed_paragraphs.iter() {
+        let file = failed_paragraph.file.as_deref().unwrap_or(file_path);
+        let error = single_line(&failed_paragraph.error.to_string());
+
+        match failed_paragraph.range {
+            Some(range) => writeln!(
+                &mut writer,
+                "{}:{}:{}: unchecked: {}",
+                file, range.start.line, range.start.column, error
+            )?,
+            None => writeln!(&mut writer, "{}: unchecked: {}", file, error)?,
+        }
+    }
     writer.flush()?;
 
     Ok(())
 }
+
+/// Joins the lines of a message with spaces.
+fn single_line(message: &str) -> String {
+    message.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Serialize)]
+struct JsonOutput<'a> {
+    problems: Vec<Problem>,
+    failed_paragraphs: Vec<JsonFailedParagraph<'a>>,
+    metadata: Metadata,
+}
+
+#[derive(Serialize)]
+struct JsonFailedParagraph<'a> {
+    file: &'a str,
+    range: Option<Range>,
+    error: String,
+}
+
+/// Writes the problems, failed paragraphs and metadata as a single JSON object to stdout.
+pub fn display_json(
+    file_path: &str,
+    mut problems: Vec<Problem>,
+    failed_paragraphs: Vec<FailedParagraph>,
+    metadata: Metadata,
+) -> Result<(), std::io::Error> {
+    for problem in problems.iter_mut() {
+        problem.file.get_or_insert_with(|| file_path.to_string());
+    }
+
+    let output = JsonOutput {
+        problems,
+        failed_paragraphs: failed_paragraphs
+            .iter()
+            .map(|failed_paragraph| JsonFailedParagraph {
+                file: failed_paragraph.file.as_deref().unwrap_or(file_path),
+                range: failed_paragraph.range,
+                error: failed_paragraph.error.to_string(),
+            })
+            .collect(),
+        metadata,
+    };
+
+    let mut writer = BufWriter::new(stdout().lock());
+    serde_json::to_writer_pretty(&mut writer, &output)?;
+    writeln!(&mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use typst_spellcheck::{problem::Problem, severity::Severity};
+
+    use super::{display_summary, group, single_line, wrap};
+
+    fn problem(match_string: &str, rule_id: &str, rule_category: &str) -> Problem {
+        Problem {
+            length: match_string.len(),
+            match_string: match_string.to_string(),
+            context: match_string.to_string(),
+            rule_category: rule_category.to_string(),
+            rule_id: rule_id.to_string(),
+            severity: Severity::Error,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn groups_are_sorted_by_size_then_key() {
+        let problems = [
+            problem("teh", "B_RULE", "TYPOS"),
+            problem("wrold", "A_RULE", "TYPOS"),
+            problem("teh", "C_RULE", "GRAMMAR"),
+            problem("teh", "C_RULE", "GRAMMAR"),
+            problem("wrold", "B_RULE", "TYPOS"),
+        ];
+
+        let groups = group(problems.iter(), |problem| &problem.rule_id);
+        let sizes: Vec<_> = groups
+            .iter()
+            .map(|(key, group)| (*key, group.len()))
+            .collect();
+
+        assert_eq!(sizes, [("B_RULE", 2), ("C_RULE", 2), ("A_RULE", 1)]);
+        // Problems keep their order within a group
+        assert_eq!(groups[0].1[0].match_string, "teh");
+        assert_eq!(groups[0].1[1].match_string, "wrold");
+    }
+
+    #[test]
+    fn summary_counts_misspelled_words() {
+        let mut misspelling = problem("wrold", "OTHER_RULE", "OTHER");
+        misspelling.issue_type = Some("misspelling".to_string());
+
+        let problems = [
+            problem("teh", "MORFOLOGIK_RULE_EN_US", "TYPOS"),
+            misspelling,
+            problem("teh", "MORFOLOGIK_RULE_EN_US", "TYPOS"),
+            problem("He go", "HE_VERB_AGR", "GRAMMAR"),
+            problem("\"quoted\"", "MORFOLOGIK_RULE_EN_US", "TYPOS"),
+        ];
+
+        let mut output = vec![];
+        display_summary(&mut output, &problems).unwrap();
+        let output = anstream::adapter::strip_str(&String::from_utf8(output).unwrap()).to_string();
+
+        assert_eq!(
+            output,
+            "Summary: 3 different misspelled word(s)\n\
+             \x20  2 teh\n\
+             \x20  1 \"quoted\"\n\
+             \x20  1 wrold\n\
+             \x20  ignore_words = [\"teh\", '\"quoted\"', \"wrold\"]\n"
+        );
+    }
+
+    #[test]
+    fn summary_without_misspellings() {
+        let mut output = vec![];
+        display_summary(&mut output, &[problem("He go", "HE_VERB_AGR", "GRAMMAR")]).unwrap();
+        let output = anstream::adapter::strip_str(&String::from_utf8(output).unwrap()).to_string();
+
+        assert_eq!(output, "Summary: 0 different misspelled word(s)\n");
+    }
+
+    #[test]
+    fn single_line_collapses_whitespace() {
+        assert_eq!(
+            single_line("  A message\nover  two\tlines.\n"),
+            "A message over two lines."
+        );
+        assert_eq!(single_line(""), "");
+    }
+
+    #[test]
+    fn wrap_without_width() {
+        let text = "A message that is never wrapped without a width";
+
+        assert_eq!(wrap(text, None, 13, 5), text);
+    }
+
+    #[test]
+    fn wrap_at_exact_width() {
+        // The first line starts at column 3 and ends exactly at the width
+        assert_eq!(wrap("abc de fg", Some(9), 3, 2), "abc de\n  fg");
+        // One more column than fits wraps the last word of the line
+        assert_eq!(wrap("abc def", Some(9), 3, 2), "abc\n  def");
+    }
+
+    #[test]
+    fn wrap_keeps_long_words() {
+        assert_eq!(
+            wrap("a incomprehensibilities b", Some(10), 0, 2),
+            "a\n  incomprehensibilities\n  b"
+        );
+        // A long first word isn't moved to a new line
+        assert_eq!(
+            wrap("incomprehensibilities", Some(10), 5, 2),
+            "incomprehensibilities"
+        );
+    }
+}
@@ -0,0 +1,717 @@
+use std::{ops, path::Path, str::Chars};
+
+use biblatex::{Bibliography, Chunk, Chunks, Pair, RawBibliography, RawChunk, RawEntry, Spanned};
+use thiserror::Error;
+use typst_syntax::{ast, Span, SyntaxNode};
+use yaml_rust::{
+    parser::Parser,
+    scanner::{ScanError, TScalarStyle},
+    Event,
+};
+
+/// A run of text in a bibliography file.
+#[derive(Debug, Clone)]
+pub struct Segment {
+    /// The byte range of the segment in the file.
+    pub range: ops::Range<usize>,
+    /// If set, the segment is markup that languagetool reads as this text.
+    pub interpret_as: Option<String>,
+}
+
+/// The text of a field that is checked in a bibliography entry, e.g. its title.
+#[derive(Debug, Clone)]
+pub struct Field {
+    pub segments: Vec<Segment>,
+}
+
+/// The formats of bibliography files supported by typst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    BibLaTeX,
+    Hayagriva,
+}
+
+impl Format {
+    /// Gets the format of a bibliography file from its extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "bib" => Some(Self::BibLaTeX),
+            "yml" | "yaml" => Some(Self::Hayagriva),
+            _ => None,
+        }
+    }
+}
+
+/// Finds the paths of bibliography files used by `bibliography` calls in the document,
+/// along with the span of the call.
+pub fn find_bibliographies(node: &SyntaxNode) -> Vec<(String, Span)> {
+    let mut bibliographies = vec![];
+
+    if let Some(call) = node.cast::<ast::FuncCall>() {
+        let is_bibliography =
+            matches!(call.callee(), ast::Expr::Ident(ident) if ident.get() == "bibliography");
+
+        if is_bibliography {
+            let paths = call.args().items().find_map(|arg| match arg {
+                ast::Arg::Pos(ast::Expr::Str(path)) => Some(vec![path.get().to_string()]),
+                ast::Arg::Pos(ast::Expr::Array(array)) => Some(
+                    array
+                        .items()
+                        .filter_map(|item| match item {
+                            ast::ArrayItem::Pos(ast::Expr::Str(path)) => {
+                                Some(path.get().to_string())
+                            }
+                            _ => None,
+                        })
+                        .collect(),
+                ),
+                _ => None,
+            });
+
+            for path in paths.unwrap_or_default() {
+                bibliographies.push((path, node.span()));
+            }
+
+            return bibliographies;
+        }
+    }
+
+    for child in node.children() {
+        bibliographies.append(&mut find_bibliographies(child));
+    }
+
+    bibliographies
+}
+
+/// An error in a bibliography file that stops some of its entries from being checked.
+#[derive(Debug, Clone, Error)]
+#[error("{message}")]
+pub struct ParseError {
+    pub message: String,
+    /// The byte range of the error in the file.
+    pub range: ops::Range<usize>,
+}
+
+/// Extracts the titles of the entries in a bibliography file.
+///
+/// Entries that can't be parsed are returned as errors, while the titles of the other
+/// entries are still extracted.
+pub fn parse_fields(contents: &str, format: Format) -> (Vec<Field>, Vec<ParseError>) {
+    match format {
+        Format::BibLaTeX => parse_biblatex(contents),
+        Format::Hayagriva => parse_hayagriva(contents),
+    }
+}
+
+/// The text languagetool reads in place of math in BibLaTeX values, the same as the
+/// default placeholder of inline equations.
+const MATH_PLACEHOLDER: &str = "x";
+
+/// Extracts the `title` fields of BibLaTeX entries.
+///
+/// Each part of a title is resolved on its own, so that `@string` abbreviations are read
+/// as their text in place of their name. A title that can't be resolved is returned as an
+/// error, while the other titles are still extracted.
+fn parse_biblatex(contents: &str) -> (Vec<Field>, Vec<ParseError>) {
+    let raw = match RawBibliography::parse(contents) {
+        Ok(raw) => raw,
+        Err(error) => return (vec![], vec![error.into()]),
+    };
+
+    let mut fields = vec![];
+    let mut errors = vec![];
+
+    let titles = raw
+        .entries
+        .iter()
+        .flat_map(|entry| &entry.v.fields)
+        .filter(|pair| pair.key.v.eq_ignore_ascii_case("title"));
+    for title in titles {
+        let segments: Result<Vec<Vec<Segment>>, _> = title
+            .value
+            .v
+            .iter()
+            .map(|raw_chunk| {
+                let chunks = resolve_chunk(raw_chunk, &raw.abbreviations)?;
+
+                Ok(match raw_chunk.v {
+                    RawChunk::Normal(_) => chunks
+                        .iter()
+                        .flat_map(|chunk| match &chunk.v {
+                            Chunk::Math(_) => vec![Segment {
+                                range: chunk.span.clone(),
+                                interpret_as: Some(MATH_PLACEHOLDER.to_string()),
+                            }],
+                            value => align(contents, chunk.span.clone(), value.get()),
+                        })
+                        .collect(),
+                    // The spans of the resolved chunks are in the definition
+                    RawChunk::Abbreviation(_) => vec![Segment {
+                        range: raw_chunk.span.clone(),
+                        interpret_as: Some(chunks.iter().map(|chunk| chunk.v.get()).collect()),
+                    }],
+                })
+            })
+            .collect();
+
+        match segments {
+            Ok(segments) => fields.push(Field {
+                segments: segments.into_iter().flatten().collect(),
+            }),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    (fields, errors)
+}
+
+/// Resolves the commands and abbreviations in a part of a BibLaTeX value.
+fn resolve_chunk(
+    raw_chunk: &Spanned<RawChunk>,
+    abbreviations: &[Pair],
+) -> Result<Chunks, ParseError> {
+    let entry = RawEntry {
+        key: Spanned::detached(""),
+        kind: Spanned::detached("misc"),
+        fields: vec![Pair::new(
+            Spanned::detached("title"),
+            Spanned::new(vec![raw_chunk.clone()], raw_chunk.span.clone()),
+        )],
+    };
+    let bibliography = Bibliography::from_raw(RawBibliography {
+        preamble: String::new(),
+        entries: vec![Spanned::new(entry, raw_chunk.span.clone())],
+        abbreviations: abbreviations.to_vec(),
+    })?;
+
+    Ok(bibliography
+        .iter()
+        .find_map(|entry| entry.get("title"))
+        .map(<[_]>::to_vec)
+        .unwrap_or_default())
+}
+
+impl From<biblatex::ParseError> for ParseError {
+    fn from(error: biblatex::ParseError) -> Self {
+        Self {
+            message: error.kind.to_string(),
+            range: error.span,
+        }
+    }
+}
+
+/// Extracts the `title` fields of Hayagriva entries and of their parents.
+///
+/// Titles can be scalars of any style, or maps with the title in their `value` key,
+/// e.g. `title: { value: .., short: .. }`.
+fn parse_hayagriva(contents: &str) -> (Vec<Field>, Vec<ParseError>) {
+    let mut events = YamlEvents::new(contents);
+    let mut fields = vec![];
+
+    let result = hayagriva_entries(&mut events, &mut fields);
+
+    let errors = match result {
+        Ok(()) => vec![],
+        Err(error) => {
+            let start = events.byte_offset(error.marker().index());
+            let end = contents[start..]
+                .chars()
+                .next()
+                .map_or(start, |char| start + char.len_utf8());
+
+            vec![ParseError {
+                message: error.to_string(),
+                range: start..end,
+            }]
+        }
+    };
+
+    (fields, errors)
+}
+
+/// Reads the entries in the top-level map of a Hayagriva file.
+fn hayagriva_entries(events: &mut YamlEvents, fields: &mut Vec<Field>) -> Result<(), ScanError> {
+    while !matches!(events.peek()?, Event::MappingStart(_) | Event::StreamEnd) {
+        events.next()?;
+    }
+    if events.next()? == Event::StreamEnd {
+        return Ok(());
+    }
+
+    while events.key()?.is_some() {
+        match events.peek()? {
+            Event::MappingStart(_) => hayagriva_entry(events, fields)?,
+            _ => events.skip()?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the map of a Hayagriva entry, and the maps of its parents.
+fn hayagriva_entry(events: &mut YamlEvents, fields: &mut Vec<Field>) -> Result<(), ScanError> {
+    events.next()?;
+
+    while let Some(key) = events.key()? {
+        match (key.as_str(), events.peek()?) {
+            ("title", Event::Scalar(..)) => fields.extend(events.scalar()?),
+            ("title", Event::MappingStart(_)) => {
+                events.next()?;
+                while let Some(key) = events.key()? {
+                    match (key.as_str(), events.peek()?) {
+                        ("value", Event::Scalar(..)) => fields.extend(events.scalar()?),
+                        _ => events.skip()?,
+                    }
+                }
+            }
+            ("parent", Event::MappingStart(_)) => hayagriva_entry(events, fields)?,
+            ("parent", Event::SequenceStart(_)) => {
+                events.next()?;
+                loop {
+                    match events.peek()? {
+                        Event::SequenceEnd => break,
+                        Event::MappingStart(_) => hayagriva_entry(events, fields)?,
+                        _ => events.skip()?,
+                    }
+                }
+                events.next()?;
+            }
+            _ => events.skip()?,
+        }
+    }
+
+    Ok(())
+}
+
+/// The events of a YAML file, along with their byte offsets in it.
+struct YamlEvents<'a> {
+    contents: &'a str,
+    parser: Parser<Chars<'a>>,
+    /// The byte offset of each character in the file, followed by its length.
+    byte_offsets: Vec<usize>,
+}
+
+impl<'a> YamlEvents<'a> {
+    fn new(contents: &'a str) -> Self {
+        let byte_offsets = contents
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain([contents.len()])
+            .collect();
+
+        Self {
+            contents,
+            parser: Parser::new(contents.chars()),
+            byte_offsets,
+        }
+    }
+
+    /// Converts a character index of a YAML marker to a byte offset.
+    fn byte_offset(&self, index: usize) -> usize {
+        self.byte_offsets
+            .get(index)
+            .copied()
+            .unwrap_or(self.contents.len())
+    }
+
+    fn next(&mut self) -> Result<Event, ScanError> {
+        self.parser.next().map(|(event, _)| event)
+    }
+
+    fn peek(&mut self) -> Result<&Event, ScanError> {
+        self.parser.peek().map(|(event, _)| event)
+    }
+
+    /// Reads the next key of a map, or the end of the map.
+    fn key(&mut self) -> Result<Option<String>, ScanError> {
+        match self.next()? {
+            Event::Scalar(key, ..) => Ok(Some(key)),
+            Event::MappingEnd | Event::StreamEnd => Ok(None),
+            // Keys that aren't scalars are skipped along with their value
+            Event::MappingStart(_) | Event::SequenceStart(_) => {
+                self.skip_to_end()?;
+                self.skip()?;
+                self.key()
+            }
+            _ => {
+                self.skip()?;
+                self.key()
+            }
+        }
+    }
+
+    /// Skips the next node, including all of its children.
+    fn skip(&mut self) -> Result<(), ScanError> {
+        match self.next()? {
+            Event::MappingStart(_) | Event::SequenceStart(_) => self.skip_to_end(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Skips the rest of the current map or sequence.
+    fn skip_to_end(&mut self) -> Result<(), ScanError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next()? {
+                Event::MappingStart(_) | Event::SequenceStart(_) => depth += 1,
+                Event::MappingEnd | Event::SequenceEnd => depth -= 1,
+                Event::StreamEnd => break,
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a scalar into a field, which spans the source up to the next event.
+    fn scalar(&mut self) -> Result<Option<Field>, ScanError> {
+        let (event, marker) = self.parser.next()?;
+        let Event::Scalar(value, style, ..) = event else {
+            return Ok(None);
+        };
+
+        let start = self.byte_offset(marker.index());
+        let next_index = self.parser.peek()?.1.index();
+        let end = self.byte_offset(next_index).max(start);
+        let source = &self.contents[start..end];
+
+        // Quotes aren't part of the value
+        let range = match style {
+            TScalarStyle::DoubleQuoted | TScalarStyle::SingleQuoted => {
+                let quote = if style == TScalarStyle::DoubleQuoted {
+                    '"'
+                } else {
+                    '\''
+                };
+                let closing = source[1..]
+                    .rfind(quote)
+                    .map_or(source.len(), |index| index + 1);
+
+                start + 1..start + closing
+            }
+            _ => start..end,
+        };
+
+        let segments = align(self.contents, range, &value);
+        Ok((!segments.is_empty()).then_some(Field { segments }))
+    }
+}
+
+/// Splits the source of a parsed value into segments, where parts of the source with the
+/// same text as the value are text, and the parts in between are markup that is read as the
+/// text of the value they differ in, e.g. an escape or an accent macro.
+///
+/// `range` is the byte range in `contents` that the value was parsed from. Source after
+/// the end of the value, such as a line break or a comment, isn't part of any segment.
+fn align(contents: &str, range: ops::Range<usize>, value: &str) -> Vec<Segment> {
+    let source: Vec<(usize, char)> = contents[range.clone()]
+        .char_indices()
+        .map(|(index, char)| (range.start + index, char))
+        .collect();
+    let value: Vec<char> = value.chars().collect();
+    let byte_offset = |index: usize| source.get(index).map_or(range.end, |(offset, _)| *offset);
+
+    let mut segments = vec![];
+    let push_text = |segments: &mut Vec<Segment>, start: usize, end: usize| {
+        if start < end {
+            segments.push(Segment {
+                range: byte_offset(start)..byte_offset(end),
+                interpret_as: None,
+            });
+        }
+    };
+
+    let mut text_start = 0;
+    let (mut source_index, mut value_index) = (0, 0);
+
+    while value_index < value.len() {
+        if source.get(source_index).map(|(_, char)| *char) == Some(value[value_index]) {
+            source_index += 1;
+            value_index += 1;
+            continue;
+        }
+
+        push_text(&mut segments, text_start, source_index);
+
+        let (source_skip, value_skip) = resync(&source[source_index..], &value[value_index..]);
+        segments.push(Segment {
+            range: byte_offset(source_index)..byte_offset(source_index + source_skip),
+            interpret_as: Some(
+                value[value_index..value_index + value_skip]
+                    .iter()
+                    .collect(),
+            ),
+        });
+
+        source_index += source_skip;
+        value_index += value_skip;
+        text_start = source_index;
+    }
+
+    push_text(&mut segments, text_start, source_index);
+
+    segments
+}
+
+/// Finds the fewest characters of the source and of the value to skip, after which they
+/// have the same text again for two characters, or for the last character of the value.
+///
+/// If they never do, the rest of both is skipped.
+fn resync(source: &[(usize, char)], value: &[char]) -> (usize, usize) {
+    let matches = |source_skip: usize, value_skip: usize| {
+        let same = |offset: usize| {
+            source.get(source_skip + offset).map(|(_, char)| char) == value.get(value_skip + offset)
+        };
+
+        value_skip < value.len() && same(0) && (value_skip + 1 == value.len() || same(1))
+    };
+
+    for total in 1..source.len() + value.len() {
+        for value_skip in 0..=total.min(value.len()) {
+            let source_skip = total - value_skip;
+
+            if source_skip <= source.len() && matches(source_skip, value_skip) {
+                return (source_skip, value_skip);
+            }
+        }
+    }
+
+    (source.len(), value.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{align, parse_fields, Field, Format};
+
+    /// Gets the text languagetool reads for a field.
+    fn interpreted(contents: &str, field: &Field) -> String {
+        field
+            .segments
+            .iter()
+            .map(|segment| {
+                segment
+                    .interpret_as
+                    .clone()
+                    .unwrap_or_else(|| contents[segment.range.clone()].to_string())
+            })
+            .collect()
+    }
+
+    fn titles(contents: &str, format: Format) -> Vec<String> {
+        let (fields, errors) = parse_fields(contents, format);
+        assert!(errors.is_empty(), "{errors:?}");
+
+        fields
+            .iter()
+            .map(|field| interpreted(contents, field))
+            .collect()
+    }
+
+    /// Gets the text segments of the fields as they are in the file.
+    fn text_segments(contents: &str, format: Format) -> Vec<&str> {
+        parse_fields(contents, format)
+            .0
+            .iter()
+            .flat_map(|field| &field.segments)
+            .filter(|segment| segment.interpret_as.is_none())
+            .map(|segment| &contents[segment.range.clone()])
+            .collect()
+    }
+
+    #[test]
+    fn biblatex_only_title_fields() {
+        let contents = r#"% title = {A comment}
+@comment{title = {A comment entry}}
+@inproceedings{key,
+  booktitle = {Proceedings},
+  subtitle = {Sub},
+  shorttitle = {Short},
+  Title = {The title},
+}
+
+title = {Text between entries}
+"#;
+
+        assert_eq!(titles(contents, Format::BibLaTeX), vec!["The title"]);
+    }
+
+    #[test]
+    fn biblatex_string_abbreviations() {
+        let contents = r#"@string{conf = "Conference on Things"}
+@inproceedings{key, title = "Proceedings of the " # conf}"#;
+
+        assert_eq!(
+            titles(contents, Format::BibLaTeX),
+            vec!["Proceedings of the Conference on Things"]
+        );
+
+        // The expanded text is read in place of the abbreviation
+        let (fields, _) = parse_fields(contents, Format::BibLaTeX);
+        let abbreviation = fields[0].segments.last().unwrap();
+        assert_eq!(&contents[abbreviation.range.clone()], "conf");
+        assert_eq!(
+            abbreviation.interpret_as.as_deref(),
+            Some("Conference on Things")
+        );
+    }
+
+    #[test]
+    fn biblatex_nested_braces_and_escapes() {
+        let contents =
+            r#"@book{key, title = {The {LaTeX} {Companion {2nd}}: Tips \& Tricks---and $x$}}"#;
+
+        assert_eq!(
+            titles(contents, Format::BibLaTeX),
+            vec!["The LaTeX Companion 2nd: Tips & Tricks—and x"]
+        );
+    }
+
+    #[test]
+    fn biblatex_accents() {
+        let contents = r#"@book{key,
+  title = {Schr{\"o}dinger's \'Etude on \c{c}a and Stra\ss e},
+}"#;
+
+        assert_eq!(
+            titles(contents, Format::BibLaTeX),
+            vec!["Schrödinger's Étude on ça and Straße"]
+        );
+        assert_eq!(
+            text_segments(contents, Format::BibLaTeX),
+            vec!["Schr", "dinger's ", "tude on ", "a and Stra", "e"]
+        );
+    }
+
+    #[test]
+    fn biblatex_entry_errors_keep_other_entries() {
+        let contents = r#"@book{first, title = "Uses an " # unknown}
+@book{second, title = {Second title}}"#;
+
+        let (fields, errors) = parse_fields(contents, Format::BibLaTeX);
+        assert_eq!(
+            fields
+                .iter()
+                .map(|field| interpreted(contents, field))
+                .collect::<Vec<_>>(),
+            vec!["Second title"]
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(&contents[errors[0].range.clone()], "unknown");
+    }
+
+    #[test]
+    fn hayagriva_forms() {
+        let contents = r#"folded:
+  type: Article
+  title: >-
+    A folded
+    title
+  author: Doe, Jane
+literal:
+  title: |
+    First line
+    Second line
+quoted:
+  title: "Café \"quoted\" \x41"
+single:
+  title: 'It''s here'
+flow:
+  title: { value: "Flow, title", short: Flow }
+flow-plain:
+  title: {value: Plain flow, verbatim: true}
+nested:
+  title:
+    value: Nested title
+    short: Nested
+plain:
+  title: Plain title # a comment
+continued:
+  title: A plain title
+    on two lines
+chapter:
+  title: Chapter title
+  parent:
+    title: Book title
+    parent:
+      - title: Series title
+"#;
+
+        assert_eq!(
+            titles(contents, Format::Hayagriva),
+            vec![
+                "A folded title",
+                "First line\nSecond line\n",
+                "Café \"quoted\" A",
+                "It's here",
+                "Flow, title",
+                "Plain flow",
+                "Nested title",
+                "Plain title",
+                "A plain title on two lines",
+                "Chapter title",
+                "Book title",
+                "Series title",
+            ]
+        );
+    }
+
+    #[test]
+    fn hayagriva_only_entry_titles() {
+        let contents = r#"entry:
+  title: Entry title
+  note:
+    title: Not a title
+  publisher:
+    name: title
+title: Not an entry
+"#;
+
+        assert_eq!(titles(contents, Format::Hayagriva), vec!["Entry title"]);
+    }
+
+    #[test]
+    fn hayagriva_text_segments_are_in_the_file() {
+        let contents = "entry:\n  title: >-\n    A folded\n    title\nquoted:\n  title: \"Caf\\u00e9 \\\"q\\\"\"\n";
+
+        // Line breaks, escapes and the text between them that doesn't match are markup
+        assert_eq!(
+            text_segments(contents, Format::Hayagriva),
+            vec!["A folded", " title", "Caf", "\"q", "\""]
+        );
+    }
+
+    #[test]
+    fn hayagriva_syntax_error() {
+        let contents = "entry:\n  title: \"Unclosed\n";
+        let (fields, errors) = parse_fields(contents, Format::Hayagriva);
+
+        assert!(fields.is_empty());
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn align_marks_differences_as_markup() {
+        let contents = r#"A \"o-umlaut -- dash"#;
+        let segments = align(contents, 0..contents.len(), "A ö-umlaut – dash");
+
+        let parts: Vec<(&str, Option<&str>)> = segments
+            .iter()
+            .map(|segment| {
+                (
+                    &contents[segment.range.clone()],
+                    segment.interpret_as.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                ("A ", None),
+                (r#"\"o"#, Some("ö")),
+                ("-umlaut ", None),
+                ("--", Some("–")),
+                (" dash", None),
+            ]
+        );
+    }
+}
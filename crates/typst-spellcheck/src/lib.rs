@@ -1,25 +1,409 @@
+use std::collections::HashMap;
+
+use preprocessor::paragraph::ParagraphKind;
+use severity::Severity;
+
+pub mod bibliography;
 pub mod preprocessor;
 pub mod problem;
 pub mod range;
+pub mod severity;
 pub mod spellchecker;
 pub mod word_count;
+#[cfg(feature = "world")]
+pub mod world;
+
+/// Maximum number of characters the free public languagetool API accepts per request.
+pub const FREE_MAX_TEXT_LENGTH: usize = 20_000;
+
+/// Maximum number of characters languagetool premium accepts per request.
+pub const PREMIUM_MAX_TEXT_LENGTH: usize = 60_000;
+
+/// Hosts of the public languagetool API, whose text limits apply.
+const PUBLIC_API_HOSTS: [&str; 2] = ["languagetool.org", "languagetoolplus.com"];
 
 /// Languagetool specific config
 #[derive(Debug, Clone, Default)]
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct LanguageToolConfig {
     pub host: String,
     pub port: u16,
+    /// Full URL of the languagetool API including the scheme and path prefix,
+    /// e.g. `https://api.languagetoolplus.com/v2`.
+    ///
+    /// Takes precedence over `host` and `port` when set.
+    pub base_url: Option<String>,
     pub language: String,
+    pub username: Option<String>,
+    pub api_key: Option<String>,
     pub disabled_rules: Option<Vec<String>>,
     pub disabled_categories: Option<Vec<String>>,
     pub picky: Option<bool>,
 }
 
+impl LanguageToolConfig {
+    /// The URL that check requests are sent to.
+    pub fn api_url(&self) -> String {
+        match self.base_url.as_ref() {
+            Some(base_url) => base_url.trim_end_matches('/').to_string(),
+            None => format!("{}:{}/v2", self.host, self.port),
+        }
+    }
+
+    /// If premium credentials have been provided.
+    pub fn is_premium(&self) -> bool {
+        self.username.is_some() && self.api_key.is_some()
+    }
+
+    /// If requests are sent to the public languagetool API rather than a self-hosted
+    /// server.
+    pub fn is_public_api(&self) -> bool {
+        let api_url = self.api_url();
+        let without_scheme = api_url
+            .split_once("://")
+            .map_or(api_url.as_str(), |(_, rest)| rest);
+        let host = without_scheme
+            .split(['/', ':'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        PUBLIC_API_HOSTS
+            .iter()
+            .any(|public| host == *public || host.ends_with(&format!(".{public}")))
+    }
+
+    /// Maximum number of bytes that can be sent in a single request, or `None` if there
+    /// is no limit.
+    ///
+    /// Only the public API has a limit, which is counted in characters. Text is measured
+    /// in bytes, of which there are never fewer than characters, so the limit is never
+    /// exceeded. Self-hosted servers have no limit, unless one is configured with
+    /// [`ChunkingConfig::max_length`].
+    pub fn max_text_length(&self) -> Option<usize> {
+        if !self.is_public_api() {
+            None
+        } else if self.is_premium() {
+            Some(PREMIUM_MAX_TEXT_LENGTH)
+        } else {
+            Some(FREE_MAX_TEXT_LENGTH)
+        }
+    }
+}
+
 /// typst-spellcheck specific config
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SpellcheckConfig {
     pub ignore_words: Option<Vec<String>>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub chunking: ChunkingConfig,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub placeholders: PlaceholderConfig,
+    /// Check the `title` and `keywords` of `#set document(..)` rules.
+    ///
+    /// Defaults to `false`.
+    pub check_document_metadata: Option<bool>,
+    /// Check the titles of entries in bibliography files used by `#bibliography(..)`.
+    ///
+    /// Defaults to `false`.
+    pub check_bibliography: Option<bool>,
+    /// Functions whose content is never checked, e.g. `["todo"]`.
+    pub skip_functions: Option<Vec<String>>,
+    /// Labels that stop the element they are attached to from being checked, without
+    /// angle brackets, e.g. `["no-spellcheck"]`.
+    pub skip_labels: Option<Vec<String>>,
+    /// Which raw blocks are checked.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub raw: RawConfig,
+    /// Which markup inside of code is checked.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub code_content: CodeContentConfig,
+    /// Functions whose content is checked separately from the sentence they are in, like
+    /// `footnote`, e.g. `["margin-note"]`. `footnote` is always included.
+    pub out_of_flow_functions: Option<Vec<String>>,
+    /// Rules for headings, used in addition to the languagetool config's rules.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub headings: ParagraphRulesConfig,
+    /// Rules for list and enum items.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub list_items: ParagraphRulesConfig,
+    /// Rules for term list items.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub terms: ParagraphRulesConfig,
+    /// Rules for the cells of tables and grids.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub table_cells: ParagraphRulesConfig,
+    /// Rules for figure captions.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub captions: ParagraphRulesConfig,
+    /// Rules for footnotes and other out of flow content.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub footnotes: ParagraphRulesConfig,
+    /// Severities that replace the ones derived from languagetool's issue types.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub severity: SeverityConfig,
     // pub ignore_headings: bool,
 }
+
+impl SpellcheckConfig {
+    /// The rules and categories disabled for a kind of paragraph, in addition to the
+    /// languagetool config's.
+    pub fn disabled_for(&self, kind: ParagraphKind) -> (Vec<String>, Vec<String>) {
+        let (rules, is_fragment) = match kind {
+            ParagraphKind::Body | ParagraphKind::Metadata => return (vec![], vec![]),
+            ParagraphKind::Heading { .. } => (&self.headings, true),
+            ParagraphKind::ListItem => (&self.list_items, true),
+            ParagraphKind::Term => (&self.terms, true),
+            ParagraphKind::TableCell => (&self.table_cells, true),
+            ParagraphKind::Caption => (&self.captions, false),
+            ParagraphKind::Footnote => (&self.footnotes, false),
+        };
+
+        let disabled_rules = rules.disabled_rules.clone().unwrap_or_else(|| {
+            if is_fragment {
+                DEFAULT_FRAGMENT_DISABLED_RULES
+                    .iter()
+                    .map(|rule| rule.to_string())
+                    .collect()
+            } else {
+                vec![]
+            }
+        });
+
+        (
+            disabled_rules,
+            rules.disabled_categories.clone().unwrap_or_default(),
+        )
+    }
+}
+
+/// Rules disabled by default for headings, list items, terms and table cells, as they
+/// are usually fragments without final punctuation.
+pub const DEFAULT_FRAGMENT_DISABLED_RULES: [&str; 1] = ["PUNCTUATION_PARAGRAPH_END"];
+
+/// Languagetool rules for a kind of paragraph.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct ParagraphRulesConfig {
+    /// Rules to disable for these paragraphs.
+    pub disabled_rules: Option<Vec<String>>,
+    /// Categories to disable for these paragraphs.
+    pub disabled_categories: Option<Vec<String>>,
+}
+
+/// Overrides of the severity of problems.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct SeverityConfig {
+    /// Severity per rule id, e.g. `{ TOO_LONG_SENTENCE = "hint" }`.
+    pub rules: Option<HashMap<String, Severity>>,
+    /// Severity per rule category id, used when the rule has no override.
+    pub categories: Option<HashMap<String, Severity>>,
+}
+
+impl SeverityConfig {
+    /// The severity of a problem with a rule and category, or `derived` if neither has
+    /// an override.
+    pub fn resolve(&self, rule_id: &str, category: &str, derived: Severity) -> Severity {
+        let rule = self.rules.as_ref().and_then(|rules| rules.get(rule_id));
+        let category = || {
+            self.categories
+                .as_ref()
+                .and_then(|categories| categories.get(category))
+        };
+
+        rule.or_else(category).copied().unwrap_or(derived)
+    }
+}
+
+/// Default minimum number of bytes in a chunk sent to languagetool.
+pub const DEFAULT_MIN_CHUNK_LENGTH: usize = 512;
+
+/// Controls how paragraphs are combined into the chunks sent to languagetool.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct ChunkingConfig {
+    /// Paragraphs shorter than this many bytes are merged with the following paragraphs.
+    ///
+    /// Defaults to [`DEFAULT_MIN_CHUNK_LENGTH`].
+    pub min_length: Option<usize>,
+    /// Paragraphs longer than this many bytes are split.
+    ///
+    /// Defaults to the text limit of the public languagetool API when it is used, see
+    /// [`LanguageToolConfig::max_text_length`], and to no limit otherwise.
+    pub max_length: Option<usize>,
+    /// If paragraphs before and after a heading may be merged into the same chunk.
+    ///
+    /// Defaults to `true`.
+    pub merge_across_headings: Option<bool>,
+    /// If merged paragraphs are sent as separate annotated text blocks instead of being
+    /// joined with a blank line of text.
+    ///
+    /// Defaults to `false`.
+    pub separate_blocks: Option<bool>,
+}
+
+/// Controls which markup inside of code is checked. Code itself is never checked.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct CodeContentConfig {
+    /// Content in `#let` bindings, e.g. `#let abstract = [..]`. Defaults to `true`.
+    pub let_bindings: Option<bool>,
+    /// Content in show rules, e.g. `#show heading: it => [..]`. Defaults to `true`.
+    pub show_rules: Option<bool>,
+    /// Content arguments of function calls and set rules, e.g. `#box[..]`. The content of
+    /// inline functions like `#emph[..]` is always checked. Defaults to `true`.
+    pub function_args: Option<bool>,
+}
+
+/// Controls which parts of raw blocks are checked.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct RawConfig {
+    /// Languages of raw blocks whose whole body is checked as prose.
+    ///
+    /// Defaults to `["text", "txt", "md", "markdown"]`.
+    pub prose_languages: Option<Vec<String>>,
+    /// Check the comments of raw blocks in known programming languages.
+    ///
+    /// Defaults to `true`.
+    pub check_comments: Option<bool>,
+}
+
+/// Text that languagetool reads in place of markup it shouldn't check.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct PlaceholderConfig {
+    /// Read in place of references to labels defined in the document without a known
+    /// prefix. Defaults to `"Section 1"`.
+    pub reference: Option<String>,
+    /// Supplements read before the number of a reference, keyed by label prefix,
+    /// e.g. `fig = "Figure"`. Extends the built in supplements.
+    pub reference_supplements: Option<HashMap<String, String>>,
+    /// Read in place of citations. Defaults to `"[1]"`.
+    pub citation: Option<String>,
+    /// Read in place of inline equations. Defaults to `"x"`.
+    pub inline_equation: Option<String>,
+    /// Read in place of block equations. Defaults to a paragraph break.
+    pub block_equation: Option<String>,
+    /// Read in place of raw text. Defaults to `"x"`.
+    pub raw: Option<String>,
+    /// Read in place of field accesses. Defaults to `"x"`.
+    pub field_access: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{
+        LanguageToolConfig, Severity, SeverityConfig, FREE_MAX_TEXT_LENGTH, PREMIUM_MAX_TEXT_LENGTH,
+    };
+
+    fn config(base_url: &str) -> LanguageToolConfig {
+        LanguageToolConfig {
+            base_url: Some(base_url.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn public_api_has_text_limit() {
+        let free = config("https://api.languagetool.org/v2");
+        assert!(free.is_public_api());
+        assert_eq!(free.max_text_length(), Some(FREE_MAX_TEXT_LENGTH));
+
+        let premium = LanguageToolConfig {
+            username: Some("user".to_string()),
+            api_key: Some("key".to_string()),
+            ..config("https://api.languagetoolplus.com/v2/")
+        };
+        assert!(premium.is_public_api());
+        assert_eq!(premium.max_text_length(), Some(PREMIUM_MAX_TEXT_LENGTH));
+    }
+
+    #[test]
+    fn self_hosted_has_no_text_limit() {
+        let host_and_port = LanguageToolConfig {
+            host: "http://127.0.0.1".to_string(),
+            port: 8081,
+            ..Default::default()
+        };
+        assert!(!host_and_port.is_public_api());
+        assert_eq!(host_and_port.max_text_length(), None);
+
+        // Only the host is compared, not the path
+        let proxied = config("https://lt.example.com/api.languagetool.org/v2");
+        assert!(!proxied.is_public_api());
+
+        let lookalike = config("https://notlanguagetool.org/v2");
+        assert!(!lookalike.is_public_api());
+    }
+
+    #[test]
+    fn severity_overrides() {
+        let overrides = SeverityConfig {
+            rules: Some(HashMap::from([
+                ("TOO_LONG_SENTENCE".to_string(), Severity::Hint),
+                ("EN_QUOTES".to_string(), Severity::Error),
+            ])),
+            categories: Some(HashMap::from([
+                ("TYPOGRAPHY".to_string(), Severity::Info),
+                ("STYLE".to_string(), Severity::Warning),
+            ])),
+        };
+
+        // A rule override takes precedence over its category's override
+        assert_eq!(
+            overrides.resolve("EN_QUOTES", "TYPOGRAPHY", Severity::Warning),
+            Severity::Error
+        );
+        assert_eq!(
+            overrides.resolve("TOO_LONG_SENTENCE", "STYLE", Severity::Hint),
+            Severity::Hint
+        );
+        // The category override applies to other rules in the category
+        assert_eq!(
+            overrides.resolve("DASH_RULE", "TYPOGRAPHY", Severity::Warning),
+            Severity::Info
+        );
+        // Otherwise the derived severity is kept
+        assert_eq!(
+            overrides.resolve("MORFOLOGIK_RULE_EN_US", "TYPOS", Severity::Error),
+            Severity::Error
+        );
+        assert_eq!(
+            SeverityConfig::default().resolve("EN_QUOTES", "TYPOGRAPHY", Severity::Warning),
+            Severity::Warning
+        );
+    }
+}
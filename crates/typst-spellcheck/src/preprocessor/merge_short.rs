@@ -1,35 +1,131 @@
-use std::borrow::Cow;
+use super::paragraph::{Paragraph, ParagraphKind, ParagraphNode};
 
-use typst_syntax::{SyntaxKind, SyntaxNode};
-
-use super::paragraph::Paragraph;
-
-pub fn merge_short(paragraphs: Vec<Paragraph>, min_length: usize) -> Vec<Paragraph> {
+/// Merges paragraphs shorter than `min_length` with the following paragraphs.
+///
+/// Paragraphs are never merged beyond `max_length`, and are only merged with the
+/// immediately preceding paragraph if it is checked with the same rules, so headings are
+/// never merged with body text.
+///
+/// If `merge_across_headings` is true, paragraphs before and after a heading may still
+/// be merged with each other.
+pub fn merge_short(
+    paragraphs: Vec<Paragraph>,
+    min_length: usize,
+    max_length: usize,
+    merge_across_headings: bool,
+) -> Vec<Paragraph> {
     let mut output: Vec<Paragraph> = vec![];
-    let mut latest_is_short = false;
 
-    for paragraph in paragraphs.to_owned().iter_mut() {
-        if latest_is_short {
-            let latest = output.last_mut().unwrap();
+    for mut paragraph in paragraphs {
+        let length = paragraph.get_text().0.len();
+        let is_heading = matches!(paragraph.kind, ParagraphKind::Heading { .. });
 
-            let break_node = SyntaxNode::leaf(SyntaxKind::Space, "\r\n\r\n");
+        let latest = output.iter_mut().rev().find(|latest| {
+            let latest_is_heading = matches!(latest.kind, ParagraphKind::Heading { .. });
+            is_heading || !latest_is_heading || !merge_across_headings
+        });
 
-            latest.nodes.push(Cow::Owned(break_node));
-            latest.nodes.append(&mut paragraph.nodes);
+        if let Some(latest) = latest {
+            let break_node = ParagraphNode::paragraph_break();
+            let latest_length = latest.get_text().0.len();
 
-            if latest.get_text().0.len() >= min_length {
-                latest_is_short = false;
+            if latest.kind.shares_rules(paragraph.kind)
+                && latest_length < min_length
+                && latest_length + break_node.text().len() + length <= max_length
+            {
+                latest.nodes.push(break_node);
+                latest.nodes.append(&mut paragraph.nodes);
+                continue;
             }
-
-            continue;
         }
 
-        if paragraph.get_text().0.len() < min_length {
-            latest_is_short = true;
-        }
-
-        output.push(paragraph.to_owned());
+        output.push(paragraph);
     }
 
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use typst_syntax::Source;
+
+    use super::merge_short;
+    use crate::{
+        preprocessor::{paragraph::ParagraphKind, preprocess},
+        SpellcheckConfig,
+    };
+
+    /// Gets the kind and interpreted text of each merged paragraph of `text`.
+    fn merged(
+        text: &str,
+        min_length: usize,
+        merge_across_headings: bool,
+    ) -> Vec<(ParagraphKind, String)> {
+        let source = Source::detached(text);
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+
+        merge_short(paragraphs, min_length, 1000, merge_across_headings)
+            .iter()
+            .map(|paragraph| (paragraph.kind, paragraph.get_interpreted_text()))
+            .collect()
+    }
+
+    #[test]
+    fn merges_until_min_length() {
+        assert_eq!(
+            merged("One.\n\nTwo.\n\nThree.\n\nFour.", 20, true),
+            [
+                (
+                    ParagraphKind::Body,
+                    "One.\r\n\r\nTwo.\r\n\r\nThree.".to_string()
+                ),
+                (ParagraphKind::Body, "Four.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn never_merges_beyond_max_length() {
+        let source = Source::detached("One.\n\nTwo.\n\nThree.");
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+
+        let text: Vec<_> = merge_short(paragraphs, 100, 12, true)
+            .iter()
+            .map(|paragraph| paragraph.get_interpreted_text())
+            .collect();
+
+        assert_eq!(text, ["One.\r\n\r\nTwo.", "Three."]);
+    }
+
+    #[test]
+    fn only_merges_with_preceding_paragraph() {
+        // The body after the list item must not be merged back into the first body
+        assert_eq!(
+            merged("Before.\n\n- Item\n\nAfter.", 100, true),
+            [
+                (ParagraphKind::Body, "Before.".to_string()),
+                (ParagraphKind::ListItem, "Item".to_string()),
+                (ParagraphKind::Body, "After.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_across_headings() {
+        assert_eq!(
+            merged("Before.\n\n= Heading\n\nAfter.", 100, true),
+            [
+                (ParagraphKind::Body, "Before.\r\n\r\nAfter.".to_string()),
+                (ParagraphKind::Heading { level: 1 }, "Heading".to_string()),
+            ]
+        );
+        assert_eq!(
+            merged("Before.\n\n= Heading\n\nAfter.", 100, false),
+            [
+                (ParagraphKind::Body, "Before.".to_string()),
+                (ParagraphKind::Heading { level: 1 }, "Heading".to_string()),
+                (ParagraphKind::Body, "After.".to_string()),
+            ]
+        );
+    }
+}
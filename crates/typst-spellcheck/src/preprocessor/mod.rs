@@ -1,10 +1,53 @@
 pub mod merge_short;
 pub mod paragraph;
+pub mod placeholder;
+pub mod raw;
+pub mod split_long;
+pub mod typography;
 
-use std::borrow::Cow;
+use std::ops;
 
-use paragraph::Paragraph;
-use typst_syntax::{SyntaxKind, SyntaxNode};
+use paragraph::{Paragraph, ParagraphKind, ParagraphNode};
+use placeholder::Placeholders;
+use typst_syntax::{
+    ast::{self, AstNode},
+    LinkedNode, SyntaxKind, SyntaxNode,
+};
+
+use crate::{RawConfig, SpellcheckConfig};
+
+/// Functions that style text inline, whose content is part of the sentence it's in.
+///
+/// Their content is checked even if other function arguments aren't.
+const INLINE_FUNCTIONS: [&str; 13] = [
+    "emph",
+    "strong",
+    "underline",
+    "overline",
+    "strike",
+    "highlight",
+    "smallcaps",
+    "sub",
+    "super",
+    "upper",
+    "lower",
+    "text",
+    "link",
+];
+
+/// State shared by every node while building paragraphs.
+struct Context<'a> {
+    placeholders: Placeholders<'a>,
+    check_document_metadata: bool,
+    /// Functions whose content is checked separately from the sentence they are in.
+    out_of_flow_functions: Vec<&'a str>,
+    raw: &'a RawConfig,
+    skip_functions: Vec<&'a str>,
+    skip_labels: Vec<&'a str>,
+    check_let_bindings: bool,
+    check_show_rules: bool,
+    check_function_args: bool,
+}
 
 /// Preprocesses a typst syntax tree to remove and modify parts
 /// that languagetool disagrees with.
@@ -13,28 +56,140 @@ use typst_syntax::{SyntaxKind, SyntaxNode};
 ///
 /// Paragraphs are separated by `Parbreak` nodes.
 ///
-/// `Raw`, `Equation`, `FieldAccess`, `RefMarker` nodes are replaced with placeholder
-/// text so lanugagetool doesn't flag them while still maintaining context.
+/// `Raw`, `Equation`, `FieldAccess`, `RefMarker` nodes and `cite` and `ref` calls are
+/// recorded as markup that languagetool reads as placeholder text, so it doesn't flag
+/// them while still maintaining context.
+///
+/// Raw blocks in a prose language are checked as their own paragraphs, as are the
+/// comments of raw blocks in a known programming language.
+///
+/// `Shorthand`, `SmartQuote`, `Escape` and `Linebreak` nodes are recorded as markup that
+/// languagetool reads as the characters typst renders them as.
+///
+/// `Hash`, `Label`, `ModuleImport`, `ModuleInclude`, `LineComment`, `BlockComment`, `Ident`, `Underscore`. `Star`, `HeadingMarker` nodes are ignored.
 ///
-/// `Hash`, `Label`, `ModuleImport`, `ModuleInclude`, `LineComment`, `BlockComment`, `Ident`, `Underscore`. `Star` nodes are ignored.
+/// Headings, list items, term items, table cells and captions are always their own
+/// paragraphs, marked with their kind.
+///
+/// The content of footnotes and the configured out of flow functions is checked as
+/// separate paragraphs, and is left out of the sentence it's in.
 ///
 /// Inside a `FuncCall`, `ShowRule`, `SetRule`, `LetBinding` node, only the contents of
-/// `Markdown` nodes are recorded.
-pub fn preprocess(root: &SyntaxNode) -> Vec<Paragraph<'_>> {
+/// `Markdown` nodes are recorded, and each kind of node can be skipped entirely in the config.
+/// The content of inline functions like `emph` and `link` is always part of the sentence
+/// it's in.
+///
+/// Calls to the configured skip functions, and elements followed by one of the
+/// configured skip labels, are dropped along with their content.
+///
+/// If enabled, the `title` and `keywords` strings of `#set document(..)` rules are
+/// recorded as their own paragraphs.
+pub fn preprocess<'a>(root: &'a SyntaxNode, config: &SpellcheckConfig) -> Vec<Paragraph<'a>> {
+    let context = Context {
+        placeholders: Placeholders::new(&config.placeholders, root),
+        check_document_metadata: config.check_document_metadata.unwrap_or(false),
+        out_of_flow_functions: ["footnote"]
+            .into_iter()
+            .chain(
+                config
+                    .out_of_flow_functions
+                    .iter()
+                    .flatten()
+                    .map(String::as_str),
+            )
+            .collect(),
+        raw: &config.raw,
+        skip_functions: config
+            .skip_functions
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect(),
+        skip_labels: config
+            .skip_labels
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect(),
+        check_let_bindings: config.code_content.let_bindings.unwrap_or(true),
+        check_show_rules: config.code_content.show_rules.unwrap_or(true),
+        check_function_args: config.code_content.function_args.unwrap_or(true),
+    };
+
     let (mut paragraphs, partial_paragraph) =
-        recursively_build_paragraphs(root, Paragraph { nodes: vec![] }, false);
+        recursively_build_paragraphs(root, Paragraph::new(ParagraphKind::Body), false, &context);
 
     if !partial_paragraph.nodes.is_empty() {
         paragraphs.push(partial_paragraph)
     }
 
+    paragraphs.retain(Paragraph::has_text);
+
     paragraphs
 }
 
+/// Gets the kind of paragraph that the text at a byte offset in a syntax tree belongs to,
+/// in the same way as [`preprocess`] marks the kind of its paragraphs, along with the
+/// byte range of the element that makes it that kind, e.g. a heading or a table cell.
+///
+/// Body text belongs to the whole tree.
+pub fn paragraph_kind_at(
+    root: &SyntaxNode,
+    offset: usize,
+    config: &SpellcheckConfig,
+) -> (ParagraphKind, ops::Range<usize>) {
+    let root = LinkedNode::new(root);
+    // The leaf that contains the character at the offset, rather than the one before it
+    let mut node = root.leaf_at(offset + 1);
+
+    let mut in_args = false;
+
+    while let Some(current) = node {
+        // The marker that out of flow content leaves in the text belongs to the call
+        // itself, rather than its arguments
+        let is_out_of_flow = in_args
+            && callee_name(&current).is_some_and(|name| {
+                name == "footnote"
+                    || config
+                        .out_of_flow_functions
+                        .iter()
+                        .flatten()
+                        .any(|function| function == name)
+            });
+
+        if is_out_of_flow {
+            return (ParagraphKind::Footnote, current.range());
+        }
+
+        // Each argument of a table is a separate cell
+        let is_cell = current.parent_kind() == Some(SyntaxKind::Args)
+            && current
+                .parent()
+                .and_then(LinkedNode::parent)
+                .and_then(|call| structure_kind(call))
+                == Some(ParagraphKind::TableCell);
+
+        if is_cell {
+            return (ParagraphKind::TableCell, current.range());
+        }
+
+        match structure_kind(&current) {
+            Some(ParagraphKind::TableCell) | None => {}
+            Some(kind) => return (kind, current.range()),
+        }
+
+        in_args = current.kind() == SyntaxKind::Args;
+        node = current.parent().cloned();
+    }
+
+    (ParagraphKind::Body, 0..root.len())
+}
+
 fn recursively_build_paragraphs<'a>(
     node: &'a SyntaxNode,
     current_paragraph: Paragraph<'a>,
     code_mode: bool,
+    context: &Context,
 ) -> (Vec<Paragraph<'a>>, Paragraph<'a>) {
     let mut paragraphs = vec![];
     let mut current_paragraph = current_paragraph;
@@ -42,6 +197,20 @@ fn recursively_build_paragraphs<'a>(
 
     let node_kind = node.kind();
 
+    if is_skipped(node, context) {
+        return (paragraphs, current_paragraph);
+    }
+
+    // Structural elements such as headings and list items are their own paragraphs
+    let outer_kind = current_paragraph.kind;
+    let structure_kind = structure_kind(node);
+    if let Some(kind) = structure_kind {
+        if !current_paragraph.nodes.is_empty() {
+            paragraphs.push(current_paragraph);
+        }
+        current_paragraph = Paragraph::new(kind);
+    }
+
     // If in code mode, only a markup block will exit it.
     // All other nodes should be ignored in code mode.
     if code_mode {
@@ -53,23 +222,127 @@ fn recursively_build_paragraphs<'a>(
             // Terminate the paragraph
             SyntaxKind::Parbreak => {
                 if !current_paragraph.nodes.is_empty() {
+                    let kind = current_paragraph.kind;
                     paragraphs.push(current_paragraph);
-                    current_paragraph = Paragraph { nodes: vec![] };
+                    current_paragraph = Paragraph::new(kind);
                 }
             }
 
-            // If there are other nodes in the paragraph, to maintain context
-            // a fake node is appended in place of the real node.
+            // To maintain context the node is appended as markup that is read as a
+            // placeholder. Paragraphs with nothing but placeholders are dropped later.
             SyntaxKind::Raw
             | SyntaxKind::Equation
             | SyntaxKind::FieldAccess
             | SyntaxKind::RefMarker => {
-                if !current_paragraph.nodes.is_empty() {
-                    let node_text = format!("`{}`", node_kind.name());
-                    let mut fake_node = SyntaxNode::leaf(SyntaxKind::Text, node_text);
-                    fake_node.synthesize(node.span());
+                if node_kind == SyntaxKind::Raw {
+                    paragraphs.append(&mut raw::raw_paragraphs(node, context.raw));
+                }
+
+                let placeholder = context.placeholders.for_node(node);
+                current_paragraph
+                    .nodes
+                    .push(ParagraphNode::markup(node, placeholder));
+
+                return (paragraphs, current_paragraph);
+            }
+
+            // References with a supplement keep the supplement as text
+            SyntaxKind::Ref
+                if node
+                    .cast::<ast::Ref>()
+                    .is_some_and(|r| r.supplement().is_some()) =>
+            {
+                let reference = node.cast::<ast::Ref>().unwrap();
+                let marker = node.children().next().unwrap();
+                let supplement = reference.supplement().unwrap().body();
+
+                return build_supplemented(
+                    marker,
+                    Some(reference.target()),
+                    supplement.to_untyped(),
+                    node,
+                    current_paragraph,
+                    context,
+                );
+            }
+
+            // Citations and references are read as a placeholder in the same way
+            SyntaxKind::FuncCall if matches!(callee_name(node), Some("cite" | "ref")) => {
+                if let Some(supplement) = call_supplement(node) {
+                    let call = node.cast::<ast::FuncCall>().unwrap();
+                    let label = match callee_name(node) {
+                        Some("ref") => call.args().items().find_map(|arg| match arg {
+                            ast::Arg::Pos(ast::Expr::Label(label)) => Some(label.get()),
+                            _ => None,
+                        }),
+                        _ => None,
+                    };
+
+                    return build_supplemented(
+                        call.callee().to_untyped(),
+                        label,
+                        supplement,
+                        node,
+                        current_paragraph,
+                        context,
+                    );
+                }
+
+                let placeholder = context.placeholders.for_call(node);
+                current_paragraph
+                    .nodes
+                    .push(ParagraphNode::markup(node, placeholder));
+
+                return (paragraphs, current_paragraph);
+            }
+
+            // The content of inline functions stays in the sentence it's in
+            SyntaxKind::FuncCall
+                if callee_name(node).is_some_and(|name| INLINE_FUNCTIONS.contains(&name)) =>
+            {
+                for markup in content_args(node) {
+                    let (mut child_paragraphs, partial_paragraph) =
+                        recursively_build_paragraphs(markup, current_paragraph, false, context);
+
+                    paragraphs.append(&mut child_paragraphs);
+                    current_paragraph = partial_paragraph;
+                }
+
+                return (paragraphs, current_paragraph);
+            }
+
+            // Footnotes and similar content are checked as their own paragraphs, and are
+            // read as nothing in the sentence they are in
+            SyntaxKind::FuncCall
+                if callee_name(node)
+                    .is_some_and(|name| context.out_of_flow_functions.contains(&name)) =>
+            {
+                let mut footnote = Paragraph::new(ParagraphKind::Footnote);
+
+                for child in node.children() {
+                    let (mut child_paragraphs, partial_paragraph) =
+                        recursively_build_paragraphs(child, footnote, true, context);
+
+                    paragraphs.append(&mut child_paragraphs);
+                    footnote = partial_paragraph;
+                }
 
-                    current_paragraph.nodes.push(Cow::Owned(fake_node));
+                if !footnote.nodes.is_empty() {
+                    paragraphs.push(footnote);
+                }
+
+                return (paragraphs, current_paragraph);
+            }
+
+            // Strings in the document's metadata are each their own paragraph
+            SyntaxKind::SetRule
+                if context.check_document_metadata && !document_strings(node).is_empty() =>
+            {
+                for string in document_strings(node) {
+                    let mut paragraph = Paragraph::new(ParagraphKind::Metadata);
+                    paragraph.nodes = string_nodes(string);
+
+                    paragraphs.push(paragraph);
                 }
 
                 return (paragraphs, current_paragraph);
@@ -84,13 +357,33 @@ fn recursively_build_paragraphs<'a>(
             | SyntaxKind::BlockComment
             | SyntaxKind::Ident
             | SyntaxKind::Underscore
-            | SyntaxKind::Star => return (paragraphs, current_paragraph),
+            | SyntaxKind::Star
+            | SyntaxKind::HeadingMarker
+            | SyntaxKind::ListMarker
+            | SyntaxKind::EnumMarker
+            | SyntaxKind::TermMarker => return (paragraphs, current_paragraph),
 
-            // Toggle code mode for code nodes
+            // Toggle code mode for code nodes, skipping them if their markup isn't checked
             SyntaxKind::FuncCall
             | SyntaxKind::ShowRule
             | SyntaxKind::SetRule
-            | SyntaxKind::LetBinding => code_mode = true,
+            | SyntaxKind::LetBinding => {
+                let checked = match node_kind {
+                    SyntaxKind::LetBinding => context.check_let_bindings,
+                    SyntaxKind::ShowRule => context.check_show_rules,
+                    _ => context.check_function_args,
+                };
+
+                if !checked {
+                    if structure_kind.is_some() {
+                        current_paragraph = Paragraph::new(outer_kind);
+                    }
+
+                    return (paragraphs, current_paragraph);
+                }
+
+                code_mode = true
+            }
 
             // Space nodes should not be appended to empty paragraphs
             SyntaxKind::Space => 'space: {
@@ -98,19 +391,47 @@ fn recursively_build_paragraphs<'a>(
                     break 'space;
                 }
 
+                // Nodes that are dropped, like skipped functions and labels, would leave
+                // two spaces in a row
+                let follows_space = current_paragraph
+                    .nodes
+                    .last()
+                    .is_some_and(|latest| latest.node.kind() == SyntaxKind::Space);
+
                 if current_paragraph.nodes.is_empty() {
                     return (vec![], current_paragraph);
-                } else {
-                    current_paragraph.nodes.push(Cow::Borrowed(node));
+                } else if !follows_space {
+                    current_paragraph.nodes.push(node.into());
                 }
             }
 
             // Containers should terminate existing paragraphs
-            SyntaxKind::Heading | SyntaxKind::ContentBlock => {
+            SyntaxKind::ContentBlock => {
+                let kind = current_paragraph.kind;
+
                 if !current_paragraph.nodes.is_empty() {
                     paragraphs.push(current_paragraph);
-                    current_paragraph = Paragraph { nodes: vec![] };
                 }
+                current_paragraph = Paragraph::new(kind);
+            }
+
+            // Typographic markup is read as the characters typst renders it as
+            SyntaxKind::Shorthand
+            | SyntaxKind::SmartQuote
+            | SyntaxKind::Escape
+            | SyntaxKind::Linebreak => {
+                let previous = current_paragraph
+                    .nodes
+                    .last()
+                    .and_then(|node| node.interpreted_text().chars().last());
+
+                if let Some(rendered) = typography::rendered_text(node, previous) {
+                    current_paragraph
+                        .nodes
+                        .push(ParagraphNode::markup(node, rendered));
+                }
+
+                return (paragraphs, current_paragraph);
             }
 
             // Other nodes should be recorded if they have text content
@@ -119,15 +440,24 @@ fn recursively_build_paragraphs<'a>(
                     break 'other;
                 }
 
-                current_paragraph.nodes.push(Cow::Borrowed(node));
+                current_paragraph.nodes.push(node.into());
             }
         }
     }
 
     // Go through this node's children and add their paragraphs
-    for child in node.children() {
+    let children: Vec<&SyntaxNode> = node.children().collect();
+    for (index, child) in children.iter().copied().enumerate() {
+        // Elements with a skipped label after them are dropped
+        let next = children[index + 1..]
+            .iter()
+            .find(|sibling| sibling.kind() != SyntaxKind::Space);
+        if next.is_some_and(|next| is_skip_label(next, context)) {
+            continue;
+        }
+
         let (mut child_groups, new_current_group) =
-            recursively_build_paragraphs(child, current_paragraph, code_mode);
+            recursively_build_paragraphs(child, current_paragraph, code_mode, context);
 
         if !child_groups.is_empty() {
             paragraphs.append(&mut child_groups);
@@ -136,16 +466,580 @@ fn recursively_build_paragraphs<'a>(
         current_paragraph = new_current_group;
     }
 
-    match node_kind {
-        // Containers should only contain it's children
-        SyntaxKind::Heading | SyntaxKind::ContentBlock => {
-            if !current_paragraph.nodes.is_empty() {
-                paragraphs.push(current_paragraph);
-                current_paragraph = Paragraph { nodes: vec![] };
-            }
+    // Containers should only contain it's children
+    if structure_kind.is_some() || node_kind == SyntaxKind::ContentBlock {
+        let kind = match structure_kind {
+            Some(_) => outer_kind,
+            None => current_paragraph.kind,
+        };
+
+        if !current_paragraph.nodes.is_empty() {
+            paragraphs.push(current_paragraph);
         }
-        _ => {}
+        current_paragraph = Paragraph::new(kind);
     }
 
     (paragraphs, current_paragraph)
 }
+
+/// If a node is a call to a skipped function, or a heading with a skipped label.
+fn is_skipped(node: &SyntaxNode, context: &Context) -> bool {
+    match node.kind() {
+        SyntaxKind::FuncCall => {
+            callee_name(node).is_some_and(|name| context.skip_functions.contains(&name))
+        }
+        SyntaxKind::Heading => node
+            .children()
+            .filter(|child| child.kind() == SyntaxKind::Markup)
+            .flat_map(|body| body.children())
+            .any(|child| is_skip_label(child, context)),
+        _ => false,
+    }
+}
+
+/// If a node is a label that stops the element it's attached to from being checked.
+fn is_skip_label(node: &SyntaxNode, context: &Context) -> bool {
+    node.cast::<ast::Label>()
+        .is_some_and(|label| context.skip_labels.contains(&label.get()))
+}
+
+/// Gets the kind of paragraph that the contents of a structural element are.
+///
+/// Table and grid cells are the content blocks inside `table` and `grid` calls, and
+/// captions are the `caption` argument of any call.
+fn structure_kind(node: &SyntaxNode) -> Option<ParagraphKind> {
+    match node.kind() {
+        SyntaxKind::Heading => Some(ParagraphKind::Heading {
+            level: node.cast::<ast::Heading>()?.depth().get(),
+        }),
+        SyntaxKind::ListItem | SyntaxKind::EnumItem => Some(ParagraphKind::ListItem),
+        SyntaxKind::TermItem => Some(ParagraphKind::Term),
+        SyntaxKind::Named if node.cast::<ast::Named>()?.name().get() == "caption" => {
+            Some(ParagraphKind::Caption)
+        }
+        SyntaxKind::FuncCall => match callee_name(node)? {
+            "table" | "grid" => Some(ParagraphKind::TableCell),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Builds a reference or citation that has a supplement.
+///
+/// The marker and the closing delimiter are recorded as markup, while the supplement
+/// is kept as text, e.g. `@intro[Chapter]` is read as "Chapter 1" and `@key[p. 7]` is
+/// read as "[1, p. 7]".
+///
+/// `label` is `None` for citations.
+fn build_supplemented<'a>(
+    marker: &'a SyntaxNode,
+    label: Option<&str>,
+    supplement: &'a SyntaxNode,
+    node: &'a SyntaxNode,
+    current_paragraph: Paragraph<'a>,
+    context: &Context,
+) -> (Vec<Paragraph<'a>>, Paragraph<'a>) {
+    let (before, after) = context.placeholders.supplemented(label);
+
+    let mut current_paragraph = current_paragraph;
+    current_paragraph
+        .nodes
+        .push(ParagraphNode::markup(marker, before));
+
+    let (paragraphs, mut current_paragraph) =
+        recursively_build_paragraphs(supplement, current_paragraph, false, context);
+
+    current_paragraph
+        .nodes
+        .push(ParagraphNode::markup(last_leaf(node), after));
+
+    (paragraphs, current_paragraph)
+}
+
+/// Gets the markup of the positional content arguments of a `FuncCall` node.
+fn content_args(node: &SyntaxNode) -> Vec<&SyntaxNode> {
+    let Some(call) = node.cast::<ast::FuncCall>() else {
+        return vec![];
+    };
+
+    call.args()
+        .items()
+        .filter_map(|arg| match arg {
+            ast::Arg::Pos(ast::Expr::Content(content)) => Some(content.body().to_untyped()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Gets the markup of the `supplement` argument of a `FuncCall` node.
+fn call_supplement(node: &SyntaxNode) -> Option<&SyntaxNode> {
+    node.cast::<ast::FuncCall>()?
+        .args()
+        .items()
+        .find_map(|arg| match arg {
+            ast::Arg::Named(named) if named.name().get() == "supplement" => match named.expr() {
+                ast::Expr::Content(content) => Some(content.body().to_untyped()),
+                _ => None,
+            },
+            _ => None,
+        })
+}
+
+/// Gets the last leaf node of a node.
+fn last_leaf(node: &SyntaxNode) -> &SyntaxNode {
+    match node.children().last() {
+        Some(child) => last_leaf(child),
+        None => node,
+    }
+}
+
+/// Splits the contents of a `Str` node into text and escape sequences, where escape
+/// sequences are markup that is read as the character they stand for.
+fn string_nodes(string: &SyntaxNode) -> Vec<ParagraphNode<'_>> {
+    let text = string.text();
+    let inner = &text[1.min(text.len())..text.len().saturating_sub(1).max(1)];
+    let mut nodes = vec![];
+    let mut text_start = 1;
+    let mut chars = inner.char_indices().peekable();
+
+    while let Some((index, char)) = chars.next() {
+        if char != '\\' {
+            continue;
+        }
+
+        let Some((_, escaped)) = chars.next() else {
+            break;
+        };
+        let mut end = index + 1 + escaped.len_utf8();
+
+        let decoded = match escaped {
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'u' if chars.peek().is_some_and(|(_, next)| *next == '{') => {
+                let close = inner[index..].find('}').map(|close| index + close);
+                end = close.map_or(inner.len(), |close| close + 1);
+                while chars
+                    .peek()
+                    .is_some_and(|(next_index, _)| *next_index < end)
+                {
+                    chars.next();
+                }
+
+                close
+                    .and_then(|close| u32::from_str_radix(&inner[index + 3..close], 16).ok())
+                    .and_then(char::from_u32)
+            }
+            _ => Some(escaped),
+        };
+
+        // Offsets in the node include the opening quote
+        if text_start < index + 1 {
+            nodes.push(ParagraphNode::from(string).slice(text_start..index + 1));
+        }
+
+        let mut escape = ParagraphNode::from(string).slice(index + 1..end + 1);
+        escape.interpret_as = Some(decoded.map(String::from).unwrap_or_default());
+        nodes.push(escape);
+
+        text_start = end + 1;
+    }
+
+    if text_start < inner.len() + 1 {
+        nodes.push(ParagraphNode::from(string).slice(text_start..inner.len() + 1));
+    }
+
+    nodes
+}
+
+/// Gets the string nodes of the `title` and `keywords` arguments of a
+/// `#set document(..)` rule.
+fn document_strings(node: &SyntaxNode) -> Vec<&SyntaxNode> {
+    let Some(set_rule) = node.cast::<ast::SetRule>() else {
+        return vec![];
+    };
+
+    let is_document = matches!(
+        set_rule.target(),
+        ast::Expr::Ident(ident) if ident.get() == "document"
+    );
+    if !is_document {
+        return vec![];
+    }
+
+    let mut strings = vec![];
+
+    for arg in set_rule.args().items() {
+        let ast::Arg::Named(named) = arg else {
+            continue;
+        };
+
+        if !matches!(named.name().get().as_str(), "title" | "keywords") {
+            continue;
+        }
+
+        let value = named.expr().to_untyped();
+        match value.kind() {
+            SyntaxKind::Str => strings.push(value),
+            SyntaxKind::Array => strings.extend(
+                value
+                    .children()
+                    .filter(|child| child.kind() == SyntaxKind::Str),
+            ),
+            _ => {}
+        }
+    }
+
+    strings
+}
+
+/// Gets the name of the function called by a `FuncCall` node, if it is called by name.
+fn callee_name(node: &SyntaxNode) -> Option<&str> {
+    match node.cast::<ast::FuncCall>()?.callee() {
+        ast::Expr::Ident(ident) => Some(ident.get().as_str()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst_syntax::Source;
+
+    use super::{
+        paragraph::{interpreted_range, ParagraphKind},
+        paragraph_kind_at, preprocess,
+    };
+    use crate::{CodeContentConfig, SpellcheckConfig};
+
+    /// Gets the text languagetool reads for each paragraph of `text`.
+    fn interpreted_paragraphs(text: &str) -> Vec<String> {
+        let source = Source::detached(text);
+        let config = SpellcheckConfig::default();
+
+        preprocess(source.root(), &config)
+            .iter()
+            .map(|paragraph| paragraph.get_interpreted_text())
+            .collect()
+    }
+
+    #[test]
+    fn headings_are_standalone() {
+        let source = Source::detached("= Intro\nSome text.\n== Details here\nMore text.");
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+
+        let kinds: Vec<_> = paragraphs.iter().map(|paragraph| paragraph.kind).collect();
+        let text: Vec<_> = paragraphs
+            .iter()
+            .map(|paragraph| paragraph.get_interpreted_text())
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [
+                ParagraphKind::Heading { level: 1 },
+                ParagraphKind::Body,
+                ParagraphKind::Heading { level: 2 },
+                ParagraphKind::Body,
+            ]
+        );
+        assert_eq!(text[0], "Intro");
+        assert_eq!(text[2], "Details here");
+    }
+
+    #[test]
+    fn structural_kinds() {
+        let source = Source::detached(
+            "- item\n/ Term: desc\n#figure(table([cell]), caption: [Caption])\n\nBody",
+        );
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+
+        let kinds: Vec<_> = paragraphs
+            .iter()
+            .map(|paragraph| (paragraph.kind, paragraph.get_interpreted_text()))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [
+                (ParagraphKind::ListItem, "item".to_string()),
+                (ParagraphKind::Term, "Term: desc".to_string()),
+                (ParagraphKind::TableCell, "cell".to_string()),
+                (ParagraphKind::Caption, "Caption".to_string()),
+                (ParagraphKind::Body, "Body".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn kind_at_offset() {
+        let text = "= Title\nBody#footnote[Note] text.\n- Item\n#table([Cell], [Other])";
+        let source = Source::detached(text);
+        let config = SpellcheckConfig::default();
+
+        let kind_at = |needle: &str| {
+            let offset = text.find(needle).unwrap();
+            let (kind, range) = paragraph_kind_at(source.root(), offset, &config);
+            (kind, &text[range])
+        };
+
+        assert_eq!(
+            kind_at("Title"),
+            (ParagraphKind::Heading { level: 1 }, "= Title")
+        );
+        assert_eq!(kind_at("Body"), (ParagraphKind::Body, text));
+        assert_eq!(kind_at("Note"), (ParagraphKind::Footnote, "footnote[Note]"));
+        // The footnote's marker is part of the body text
+        assert_eq!(kind_at("footnote"), (ParagraphKind::Body, text));
+        assert_eq!(kind_at("Item"), (ParagraphKind::ListItem, "- Item"));
+        assert_eq!(kind_at("Cell"), (ParagraphKind::TableCell, "[Cell]"));
+        assert_eq!(kind_at("Other"), (ParagraphKind::TableCell, "[Other]"));
+    }
+
+    #[test]
+    fn footnote_is_separate() {
+        let source = Source::detached("Some text#footnote[A note.] continues.");
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+
+        let kinds: Vec<_> = paragraphs
+            .iter()
+            .map(|paragraph| (paragraph.kind, paragraph.get_interpreted_text()))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            [
+                (ParagraphKind::Footnote, "A note.".to_string()),
+                (ParagraphKind::Body, "Some text continues.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn typography_is_rendered() {
+        let paragraphs =
+            interpreted_paragraphs("He said \"hi\" -- it's done... A\\#b\\u{1F600} soft-?ware");
+
+        assert_eq!(
+            paragraphs,
+            ["He said \u{201C}hi\u{201D} \u{2013} it\u{2019}s done\u{2026} A#b\u{1F600} software"]
+        );
+    }
+
+    #[test]
+    fn match_across_escape() {
+        let source = Source::detached("Say A\\#b\\u{1F600} now.");
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+        let (text, node_contributions) = paragraphs[0].get_text();
+        let interpreted_text = paragraphs[0].get_interpreted_text();
+
+        let start = text.find('A').unwrap();
+        let end = text.find(" now").unwrap();
+        let range = interpreted_range(&node_contributions, start..end).unwrap();
+
+        assert_eq!(&interpreted_text[range], "A#b\u{1F600}");
+    }
+
+    #[test]
+    fn document_metadata_escapes() {
+        let source = Source::detached(r#"#set document(title: "Say \"hi\" \u{1F600}")"#);
+        let config = SpellcheckConfig {
+            check_document_metadata: Some(true),
+            ..Default::default()
+        };
+        let paragraphs = preprocess(source.root(), &config);
+
+        assert_eq!(paragraphs[0].get_interpreted_text(), "Say \"hi\" \u{1F600}");
+        assert_eq!(
+            paragraphs[0].range(&source).map(|range| range.to_string()),
+            Some("line 1, column 23-43".to_string())
+        );
+    }
+
+    #[test]
+    fn content_in_code() {
+        let text =
+            "#let abstract = [An abstract.]\n\n#show heading: it => [Chapter]\n\n#box[Boxed]";
+
+        assert_eq!(
+            interpreted_paragraphs(text),
+            ["An abstract.", "Chapter", "Boxed"]
+        );
+
+        let paragraphs_without = |code_content: CodeContentConfig| -> Vec<String> {
+            let source = Source::detached(text);
+            let config = SpellcheckConfig {
+                code_content,
+                ..Default::default()
+            };
+
+            preprocess(source.root(), &config)
+                .iter()
+                .map(|paragraph| paragraph.get_interpreted_text())
+                .collect()
+        };
+
+        assert_eq!(
+            paragraphs_without(CodeContentConfig {
+                let_bindings: Some(false),
+                ..Default::default()
+            }),
+            ["Chapter", "Boxed"]
+        );
+        assert_eq!(
+            paragraphs_without(CodeContentConfig {
+                show_rules: Some(false),
+                ..Default::default()
+            }),
+            ["An abstract.", "Boxed"]
+        );
+        assert_eq!(
+            paragraphs_without(CodeContentConfig {
+                function_args: Some(false),
+                ..Default::default()
+            }),
+            ["An abstract.", "Chapter"]
+        );
+    }
+
+    #[test]
+    fn inline_functions_without_function_args() {
+        assert_eq!(
+            interpreted_paragraphs("Some #emph[important] text."),
+            ["Some important text."]
+        );
+
+        let source = Source::detached(
+            "Some #emph[important] and #strong[bold] text with a #link(\"https://typst.app\")[link] and a #box[box].",
+        );
+        let config = SpellcheckConfig {
+            code_content: CodeContentConfig {
+                function_args: Some(false),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let paragraphs: Vec<_> = preprocess(source.root(), &config)
+            .iter()
+            .map(|paragraph| paragraph.get_interpreted_text())
+            .collect();
+
+        assert_eq!(
+            paragraphs,
+            ["Some important and bold text with a link and a ."]
+        );
+    }
+
+    #[test]
+    fn skipped_functions_and_labels() {
+        let source = Source::detached(
+            "= Skipped <no-spellcheck>\n\nKept #todo[skipped] text.\n\n#figure([Skipped]) <no-spellcheck>\n\n= Kept",
+        );
+        let config = SpellcheckConfig {
+            skip_functions: Some(vec!["todo".to_string()]),
+            skip_labels: Some(vec!["no-spellcheck".to_string()]),
+            ..Default::default()
+        };
+        let paragraphs: Vec<_> = preprocess(source.root(), &config)
+            .iter()
+            .map(|paragraph| paragraph.get_interpreted_text())
+            .collect();
+
+        assert_eq!(paragraphs, ["Kept text.", "Kept"]);
+    }
+
+    #[test]
+    fn raw_blocks() {
+        let paragraphs = interpreted_paragraphs(
+            "```md\nSome prose.\n```\n\n```rust\n// A comment\n// continued.\nlet x = \"// no\"; /* Block */\n```\n\n```unknown\n// skipped\n```",
+        );
+
+        assert_eq!(paragraphs, ["Some prose.", "A comment continued.", "Block"]);
+    }
+
+    #[test]
+    fn reference_without_supplement() {
+        let paragraphs = interpreted_paragraphs("See @fig-results for details.");
+
+        assert_eq!(paragraphs, ["See Figure 1 for details."]);
+    }
+
+    #[test]
+    fn placeholder_at_paragraph_start() {
+        let paragraphs = interpreted_paragraphs(
+            "@fig-results shows the trend.\n\n$x$ is positive.\n\n#cite(<smith>) agrees.",
+        );
+
+        assert_eq!(
+            paragraphs,
+            ["Figure 1 shows the trend.", "x is positive.", "[1] agrees."]
+        );
+    }
+
+    #[test]
+    fn placeholder_only_paragraphs_are_dropped() {
+        let paragraphs = interpreted_paragraphs("Some text.\n\n$ x + y $\n\n@fig-results");
+
+        assert_eq!(paragraphs, ["Some text."]);
+    }
+
+    #[test]
+    fn reference_to_document_label() {
+        let paragraphs = interpreted_paragraphs("= Intro <intro>\n\nSee @intro for details.");
+
+        assert_eq!(paragraphs.last().unwrap(), "See Section 1 for details.");
+    }
+
+    #[test]
+    fn citation_without_supplement() {
+        let paragraphs = interpreted_paragraphs("As shown by @smith2020 and #cite(<jones>).");
+
+        assert_eq!(paragraphs, ["As shown by [1] and [1]."]);
+    }
+
+    #[test]
+    fn reference_with_supplement() {
+        let paragraphs = interpreted_paragraphs("See @fig-results[Chart] for details.");
+
+        assert_eq!(paragraphs, ["See Chart 1 for details."]);
+    }
+
+    #[test]
+    fn citation_with_supplement() {
+        let paragraphs = interpreted_paragraphs("As shown by @smith2020[p. 7], it works.");
+
+        assert_eq!(paragraphs, ["As shown by [1, p. 7], it works."]);
+    }
+
+    #[test]
+    fn ref_call_with_supplement() {
+        let paragraphs =
+            interpreted_paragraphs("See #ref(<sec-intro>, supplement: [Chapter]) for details.");
+
+        assert_eq!(paragraphs, ["See Chapter 1 for details."]);
+    }
+
+    #[test]
+    fn cite_call_with_supplement() {
+        let paragraphs =
+            interpreted_paragraphs("As shown by #cite(<smith2020>, supplement: [p. 7]), it works.");
+
+        assert_eq!(paragraphs, ["As shown by [1, p. 7], it works."]);
+    }
+
+    #[test]
+    fn supplement_maps_to_source() {
+        let text = "See @fig-results[Chart] for details.";
+        let source = Source::detached(text);
+        let config = SpellcheckConfig::default();
+        let paragraphs = preprocess(source.root(), &config);
+
+        let supplement = paragraphs[0]
+            .nodes
+            .iter()
+            .find(|node| node.interpret_as.is_none() && node.text() == "Chart")
+            .unwrap();
+
+        assert_eq!(&text[supplement.source_range(&source).unwrap()], "Chart");
+    }
+}
@@ -1,40 +1,404 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, ops};
 
-use typst_syntax::{Span, SyntaxNode};
+use languagetool_rust::check::{Data, DataAnnotation};
+use typst_syntax::{Source, Span, SyntaxKind, SyntaxNode};
+
+use crate::range::Range;
 
 #[derive(Debug, Clone)]
 pub struct Paragraph<'a> {
-    pub nodes: Vec<Cow<'a, SyntaxNode>>,
+    pub kind: ParagraphKind,
+    pub nodes: Vec<ParagraphNode<'a>>,
+}
+
+/// The part of the document a paragraph was taken from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParagraphKind {
+    Body,
+    Heading {
+        /// The depth of the heading, starting at 1.
+        level: usize,
+    },
+    /// List and enum items.
+    ListItem,
+    /// Term list items.
+    Term,
+    /// The cells of tables and grids.
+    TableCell,
+    /// Figure captions.
+    Caption,
+    Footnote,
+    /// Strings in `#set document(..)` rules.
+    Metadata,
+}
+
+impl ParagraphKind {
+    /// If paragraphs of both kinds are checked with the same rules, so they can be
+    /// merged into the same chunk.
+    pub fn shares_rules(self, other: Self) -> bool {
+        std::mem::discriminant(&self) == std::mem::discriminant(&other)
+    }
+}
+
+/// A node that contributes text to a paragraph.
+#[derive(Debug, Clone)]
+pub struct ParagraphNode<'a> {
+    pub node: Cow<'a, SyntaxNode>,
+    /// The byte range within the node's span that this node's text was taken from.
+    ///
+    /// `None` if the node covers its whole span.
+    pub span_range: Option<ops::Range<usize>>,
+    /// If set, the node's text is sent to languagetool as markup that is read as this text.
+    pub interpret_as: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct NodeContribution {
     pub span: Span,
+    /// Number of bytes from the start of the span to the start of the contributed text.
+    pub span_offset: usize,
     pub offset: usize,
     pub length: usize,
+    /// Offset of the node's text in the interpreted text.
+    pub interpreted_offset: usize,
+    /// Length of the node's text in the interpreted text, which differs from `length` for
+    /// markup nodes.
+    pub interpreted_length: usize,
+}
+
+impl NodeContribution {
+    /// Maps an offset in the paragraph's text inside of this node to an offset in the
+    /// interpreted text.
+    ///
+    /// Offsets inside of markup can't be mapped exactly, so they map to the start or end
+    /// of the markup's interpretation.
+    fn interpreted(&self, offset: usize, is_end: bool) -> usize {
+        if self.length == self.interpreted_length {
+            self.interpreted_offset + offset - self.offset
+        } else if is_end {
+            self.interpreted_offset + self.interpreted_length
+        } else {
+            self.interpreted_offset
+        }
+    }
+}
+
+/// Maps a byte range in a paragraph's text, as returned by [`Paragraph::get_text`], to a
+/// byte range in its interpreted text.
+pub fn interpreted_range(
+    node_contributions: &[NodeContribution],
+    range: ops::Range<usize>,
+) -> Option<ops::Range<usize>> {
+    let start = node_contributions.iter().find(|contribution| {
+        contribution.length != 0
+            && range.start >= contribution.offset
+            && range.start < contribution.offset + contribution.length
+    })?;
+    let end = node_contributions.iter().find(|contribution| {
+        contribution.length != 0
+            && range.end >= contribution.offset
+            && range.end <= contribution.offset + contribution.length
+    })?;
+
+    Some(start.interpreted(range.start, false)..end.interpreted(range.end, true))
 }
 
 impl<'a> Paragraph<'a> {
+    pub fn new(kind: ParagraphKind) -> Self {
+        Self {
+            kind,
+            nodes: vec![],
+        }
+    }
+
+    /// If any of the paragraph's nodes contribute text that isn't markup or whitespace.
+    pub fn has_text(&self) -> bool {
+        self.nodes.iter().any(|paragraph_node| {
+            paragraph_node.interpret_as.is_none() && !paragraph_node.text().trim().is_empty()
+        })
+    }
+
+    /// Gets the source text of the paragraph's nodes, including markup, along with where
+    /// each node contributed to the text.
+    ///
+    /// This is the text that languagetool's match offsets refer to.
     pub fn get_text(&self) -> (String, Vec<NodeContribution>) {
         let mut output = String::new();
         let mut node_contributions = vec![];
+        let mut interpreted_offset = 0;
 
-        for node in self.nodes.iter() {
-            let text = node.text().as_str();
+        for paragraph_node in self.nodes.iter() {
+            let text = paragraph_node.text();
             let offset = output.len();
             let length = text.len();
+            let interpreted_length = paragraph_node.interpreted_text().len();
 
-            output.push_str(text);
+            output.push_str(&text);
 
             let node_contribution = NodeContribution {
-                span: node.span(),
+                span: paragraph_node.node.span(),
+                span_offset: paragraph_node
+                    .span_range
+                    .as_ref()
+                    .map_or(0, |range| range.start),
                 offset,
                 length,
+                interpreted_offset,
+                interpreted_length,
             };
 
             node_contributions.push(node_contribution);
+            interpreted_offset += interpreted_length;
         }
 
         (output, node_contributions)
     }
+
+    /// Gets the paragraph as annotated text, where markup nodes are hidden from
+    /// languagetool behind their interpretation.
+    ///
+    /// If `separate_blocks` is set, breaks between merged paragraphs are also markup
+    /// so each merged paragraph is its own block.
+    ///
+    /// Offsets into the annotated text match the offsets of [`Paragraph::get_text`].
+    pub fn get_data(&self, separate_blocks: bool) -> Data {
+        let mut annotations = vec![];
+        let mut text = String::new();
+
+        for paragraph_node in self.nodes.iter() {
+            let interpret_as = match paragraph_node.interpret_as.as_ref() {
+                Some(interpret_as) => interpret_as.as_str(),
+                None if separate_blocks && paragraph_node.is_break() => "\n\n",
+                None => {
+                    text.push_str(&paragraph_node.text());
+                    continue;
+                }
+            };
+
+            if !text.is_empty() {
+                annotations.push(DataAnnotation::new_text(std::mem::take(&mut text)));
+            }
+
+            annotations.push(DataAnnotation::new_interpreted_markup(
+                paragraph_node.text().into_owned(),
+                interpret_as.to_string(),
+            ));
+        }
+
+        if !text.is_empty() {
+            annotations.push(DataAnnotation::new_text(text));
+        }
+
+        annotations.into_iter().collect()
+    }
+
+    /// Gets the text languagetool reads for this paragraph, with markup nodes
+    /// replaced by their interpretation.
+    pub fn get_interpreted_text(&self) -> String {
+        self.nodes
+            .iter()
+            .map(|paragraph_node| paragraph_node.interpreted_text())
+            .collect()
+    }
+
+    /// Gets the range in the source that this paragraph's nodes cover.
+    ///
+    /// Synthetic nodes without a span in the source are skipped.
+    pub fn range(&self, source: &Source) -> Option<Range> {
+        Range::from_byte_range(source, self.byte_range(source)?)
+    }
+
+    /// Gets the byte range in the source that this paragraph's nodes cover.
+    pub fn byte_range(&self, source: &Source) -> Option<ops::Range<usize>> {
+        let mut node_ranges = self
+            .nodes
+            .iter()
+            .filter_map(|paragraph_node| paragraph_node.source_range(source));
+
+        let first = node_ranges.next()?;
+        let (start, end) = node_ranges.fold((first.start, first.end), |(start, end), range| {
+            (start.min(range.start), end.max(range.end))
+        });
+
+        Some(start..end)
+    }
+}
+
+impl<'a> ParagraphNode<'a> {
+    /// Creates a node that is sent to languagetool as markup read as `interpret_as`.
+    pub fn markup(node: &'a SyntaxNode, interpret_as: impl Into<String>) -> Self {
+        Self {
+            node: Cow::Borrowed(node),
+            span_range: None,
+            interpret_as: Some(interpret_as.into()),
+        }
+    }
+
+    /// Creates a synthetic text node from a byte range of this node's text that keeps
+    /// the span of this node.
+    pub fn slice(&self, range: ops::Range<usize>) -> Self {
+        let base_offset = self.span_range.as_ref().map_or(0, |range| range.start);
+
+        let mut node = SyntaxNode::leaf(SyntaxKind::Text, &self.text()[range.clone()]);
+        node.synthesize(self.node.span());
+
+        Self {
+            node: Cow::Owned(node),
+            span_range: Some(base_offset + range.start..base_offset + range.end),
+            interpret_as: None,
+        }
+    }
+
+    /// Gets the source text of the node, including the text of any children.
+    pub fn text(&self) -> Cow<'_, str> {
+        if self.node.text().is_empty() {
+            Cow::Owned(SyntaxNode::clone(&self.node).into_text().to_string())
+        } else {
+            Cow::Borrowed(self.node.text().as_str())
+        }
+    }
+
+    /// Gets the text that languagetool reads for the node.
+    pub fn interpreted_text(&self) -> Cow<'_, str> {
+        match self.interpret_as.as_ref() {
+            Some(interpret_as) => Cow::Borrowed(interpret_as.as_str()),
+            None => self.text(),
+        }
+    }
+
+    /// Creates the synthetic node that separates merged paragraphs.
+    pub fn paragraph_break() -> Self {
+        SyntaxNode::leaf(SyntaxKind::Space, "\r\n\r\n").into()
+    }
+
+    /// If this node separates merged paragraphs.
+    pub fn is_break(&self) -> bool {
+        self.node.kind() == SyntaxKind::Space && self.node.span().is_detached()
+    }
+
+    /// Gets the byte range in the source that this node was taken from.
+    pub fn source_range(&self, source: &Source) -> Option<ops::Range<usize>> {
+        let span_range = source.range(self.node.span())?;
+
+        match self.span_range.as_ref() {
+            Some(range) => Some(span_range.start + range.start..span_range.start + range.end),
+            None => Some(span_range),
+        }
+    }
+}
+
+impl<'a> From<&'a SyntaxNode> for ParagraphNode<'a> {
+    fn from(node: &'a SyntaxNode) -> Self {
+        Self {
+            node: Cow::Borrowed(node),
+            span_range: None,
+            interpret_as: None,
+        }
+    }
+}
+
+impl<'a> From<SyntaxNode> for ParagraphNode<'a> {
+    fn from(node: SyntaxNode) -> Self {
+        Self {
+            node: Cow::Owned(node),
+            span_range: None,
+            interpret_as: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use languagetool_rust::check::Data;
+    use typst_syntax::Source;
+
+    use crate::{
+        preprocessor::{merge_short::merge_short, preprocess},
+        SpellcheckConfig,
+    };
+
+    const TEXT: &str =
+        "= Café intro\n\nSome $x^2$ text \\#1 -- with \"quotes\"...\n\nSecond paragraph~here.";
+
+    /// Joins the text and markup of annotated data, which is what languagetool's offsets
+    /// refer to.
+    fn joined(data: &Data) -> String {
+        data.annotation
+            .iter()
+            .filter_map(|annotation| annotation.text.as_deref().or(annotation.markup.as_deref()))
+            .collect()
+    }
+
+    /// Gets the markup of annotated data along with what it is read as.
+    fn markup(data: &Data) -> Vec<(&str, &str)> {
+        data.annotation
+            .iter()
+            .filter_map(|annotation| {
+                Some((
+                    annotation.markup.as_deref()?,
+                    annotation.interpret_as.as_deref()?,
+                ))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn data_joins_to_text() {
+        let source = Source::detached(TEXT);
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+        let merged = merge_short(paragraphs.clone(), 1000, 1000, true);
+
+        for paragraph in paragraphs.iter().chain(&merged) {
+            let (text, _) = paragraph.get_text();
+
+            for separate_blocks in [false, true] {
+                assert_eq!(joined(&paragraph.get_data(separate_blocks)), text);
+            }
+        }
+    }
+
+    #[test]
+    fn markup_is_interpreted() {
+        let source = Source::detached(TEXT);
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+        let data = paragraphs[1].get_data(false);
+
+        assert_eq!(
+            markup(&data),
+            vec![
+                ("$x^2$", "x"),
+                ("\\#", "#"),
+                ("--", "–"),
+                ("\"", "“"),
+                ("\"", "”"),
+                ("...", "…"),
+            ]
+        );
+        assert_eq!(
+            markup(&paragraphs[2].get_data(false)),
+            vec![("~", "\u{a0}")]
+        );
+    }
+
+    #[test]
+    fn separate_blocks_hide_paragraph_breaks() {
+        let source = Source::detached("First paragraph.\n\nSecond paragraph.");
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+        let merged = merge_short(paragraphs, 1000, 1000, true);
+        assert_eq!(merged.len(), 1);
+
+        // Without separate blocks the break is text that languagetool reads as is
+        let data = merged[0].get_data(false);
+        assert_eq!(data.annotation.len(), 1);
+        assert!(markup(&data).is_empty());
+
+        // With separate blocks it is markup read as a paragraph break
+        let data = merged[0].get_data(true);
+        assert_eq!(markup(&data), vec![("\r\n\r\n", "\n\n")]);
+        assert_eq!(data.annotation[0].text.as_deref(), Some("First paragraph."));
+        assert_eq!(
+            data.annotation[2].text.as_deref(),
+            Some("Second paragraph.")
+        );
+    }
 }
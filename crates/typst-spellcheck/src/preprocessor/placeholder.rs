@@ -0,0 +1,185 @@
+use std::collections::HashSet;
+
+use typst_syntax::{ast, SyntaxKind, SyntaxNode};
+
+use crate::PlaceholderConfig;
+
+/// Supplements that are read before the number of a reference, keyed by label prefix.
+const DEFAULT_REFERENCE_SUPPLEMENTS: [(&str, &str); 9] = [
+    ("fig", "Figure"),
+    ("tab", "Table"),
+    ("tbl", "Table"),
+    ("sec", "Section"),
+    ("eq", "Equation"),
+    ("lst", "Listing"),
+    ("ch", "Chapter"),
+    ("chap", "Chapter"),
+    ("app", "Appendix"),
+];
+
+const DEFAULT_REFERENCE: &str = "Section 1";
+const DEFAULT_CITATION: &str = "[1]";
+const DEFAULT_INLINE_EQUATION: &str = "x";
+const DEFAULT_BLOCK_EQUATION: &str = "\n\n";
+const DEFAULT_RAW: &str = "x";
+const DEFAULT_FIELD_ACCESS: &str = "x";
+
+/// Builds the text that languagetool reads in place of markup, so the
+/// placeholder reads naturally in the sentence around it.
+pub struct Placeholders<'a> {
+    config: &'a PlaceholderConfig,
+    /// Labels that are defined in the document.
+    labels: HashSet<&'a str>,
+}
+
+impl<'a> Placeholders<'a> {
+    pub fn new(config: &'a PlaceholderConfig, root: &'a SyntaxNode) -> Self {
+        let mut labels = HashSet::new();
+        collect_labels(root, &mut labels);
+
+        Self { config, labels }
+    }
+
+    /// The placeholder for `Raw`, `Equation`, `FieldAccess` and `RefMarker` nodes.
+    pub fn for_node(&self, node: &SyntaxNode) -> String {
+        match node.kind() {
+            SyntaxKind::Equation => {
+                let block = node
+                    .cast::<ast::Equation>()
+                    .is_some_and(|equation| equation.block());
+
+                self.equation(block)
+            }
+            SyntaxKind::RefMarker => self.reference(node.text().trim_start_matches('@')),
+            SyntaxKind::FieldAccess => self
+                .config
+                .field_access
+                .as_deref()
+                .unwrap_or(DEFAULT_FIELD_ACCESS)
+                .to_string(),
+            _ => self
+                .config
+                .raw
+                .as_deref()
+                .unwrap_or(DEFAULT_RAW)
+                .to_string(),
+        }
+    }
+
+    /// The placeholder for a `cite` or `ref` function call.
+    pub fn for_call(&self, node: &SyntaxNode) -> String {
+        let Some(call) = node.cast::<ast::FuncCall>() else {
+            return self.citation();
+        };
+
+        let label = call.args().items().find_map(|arg| match arg {
+            ast::Arg::Pos(ast::Expr::Label(label)) => Some(label.get()),
+            _ => None,
+        });
+
+        match (call.callee(), label) {
+            (ast::Expr::Ident(ident), Some(label)) if ident.get() == "ref" => self.reference(label),
+            _ => self.citation(),
+        }
+    }
+
+    /// The placeholders before and after the supplement of a reference to `label`, or of
+    /// a citation if `label` is `None`.
+    ///
+    /// References read as the supplement followed by the number, and citations read
+    /// with the supplement inside of the brackets.
+    pub fn supplemented(&self, label: Option<&str>) -> (String, String) {
+        match label {
+            Some(label) if !self.is_citation(label) => (String::new(), " 1".to_string()),
+            _ => {
+                let citation = self.citation();
+
+                match citation.strip_suffix(']') {
+                    Some(open) => (format!("{open}, "), "]".to_string()),
+                    None => (format!("{citation} "), String::new()),
+                }
+            }
+        }
+    }
+
+    /// The placeholder for a reference to `label`.
+    ///
+    /// Labels with a known prefix such as `fig-` read as e.g. "Figure 1", other labels
+    /// defined in the document read as a generic reference, and labels that aren't
+    /// defined in the document are assumed to be bibliography keys and read as a citation.
+    pub fn reference(&self, label: &str) -> String {
+        if let Some(supplement) = self.supplement(label) {
+            return format!("{supplement} 1");
+        }
+
+        if !self.is_citation(label) {
+            self.config
+                .reference
+                .as_deref()
+                .unwrap_or(DEFAULT_REFERENCE)
+                .to_string()
+        } else {
+            self.citation()
+        }
+    }
+
+    /// The placeholder for a citation.
+    pub fn citation(&self) -> String {
+        self.config
+            .citation
+            .as_deref()
+            .unwrap_or(DEFAULT_CITATION)
+            .to_string()
+    }
+
+    /// The placeholder for an equation.
+    pub fn equation(&self, block: bool) -> String {
+        let placeholder = if block {
+            self.config
+                .block_equation
+                .as_deref()
+                .unwrap_or(DEFAULT_BLOCK_EQUATION)
+        } else {
+            self.config
+                .inline_equation
+                .as_deref()
+                .unwrap_or(DEFAULT_INLINE_EQUATION)
+        };
+
+        placeholder.to_string()
+    }
+
+    /// If a reference to `label` is a citation of a bibliography entry.
+    fn is_citation(&self, label: &str) -> bool {
+        self.supplement(label).is_none() && !self.labels.contains(label)
+    }
+
+    /// The supplement for a label based on its prefix, e.g. `fig` for `fig-results`.
+    fn supplement(&self, label: &str) -> Option<&str> {
+        let (prefix, _) = label.split_once(['-', ':', '_', '.'])?;
+
+        if let Some(supplement) = self
+            .config
+            .reference_supplements
+            .as_ref()
+            .and_then(|supplements| supplements.get(prefix))
+        {
+            return Some(supplement);
+        }
+
+        DEFAULT_REFERENCE_SUPPLEMENTS
+            .iter()
+            .find(|(key, _)| *key == prefix)
+            .map(|(_, supplement)| *supplement)
+    }
+}
+
+fn collect_labels<'a>(node: &'a SyntaxNode, labels: &mut HashSet<&'a str>) {
+    if let Some(label) = node.cast::<ast::Label>() {
+        labels.insert(label.get());
+    }
+
+    for child in node.children() {
+        collect_labels(child, labels);
+    }
+}
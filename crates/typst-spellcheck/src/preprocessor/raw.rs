@@ -0,0 +1,477 @@
+use std::ops;
+
+use typst_syntax::{SyntaxKind, SyntaxNode};
+
+use super::paragraph::{Paragraph, ParagraphKind, ParagraphNode};
+use crate::RawConfig;
+
+/// Languages whose raw blocks are checked as prose by default.
+const DEFAULT_PROSE_LANGUAGES: [&str; 4] = ["text", "txt", "md", "markdown"];
+
+/// How comments are written in a programming language.
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: &'static [(&'static str, &'static str)],
+    single_quotes: SingleQuotes,
+}
+
+/// What single quotes mean in the code of a programming language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SingleQuotes {
+    /// Single quotes aren't special, like apostrophes in typst markup.
+    None,
+    /// Single quotes delimit strings, like double quotes.
+    String,
+    /// Single quotes delimit character literals such as `'"'`, and are part of the code
+    /// otherwise, like Rust lifetimes.
+    Char,
+}
+
+const TYPST: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: &[("/*", "*/")],
+    single_quotes: SingleQuotes::None,
+};
+const C_LIKE: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: &[("/*", "*/")],
+    single_quotes: SingleQuotes::Char,
+};
+const JS_LIKE: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: &[("/*", "*/")],
+    single_quotes: SingleQuotes::String,
+};
+const HASH: CommentSyntax = CommentSyntax {
+    line: &["#"],
+    block: &[],
+    single_quotes: SingleQuotes::String,
+};
+const LUA: CommentSyntax = CommentSyntax {
+    line: &["--"],
+    block: &[],
+    single_quotes: SingleQuotes::String,
+};
+const ADA: CommentSyntax = CommentSyntax {
+    line: &["--"],
+    block: &[],
+    single_quotes: SingleQuotes::Char,
+};
+const HASKELL: CommentSyntax = CommentSyntax {
+    line: &["--"],
+    block: &[("{-", "-}")],
+    single_quotes: SingleQuotes::Char,
+};
+const SQL: CommentSyntax = CommentSyntax {
+    line: &["--"],
+    block: &[("/*", "*/")],
+    single_quotes: SingleQuotes::String,
+};
+const PERCENT: CommentSyntax = CommentSyntax {
+    line: &["%"],
+    block: &[],
+    single_quotes: SingleQuotes::None,
+};
+const SEMICOLON: CommentSyntax = CommentSyntax {
+    line: &[";"],
+    block: &[],
+    single_quotes: SingleQuotes::None,
+};
+const CSS: CommentSyntax = CommentSyntax {
+    line: &[],
+    block: &[("/*", "*/")],
+    single_quotes: SingleQuotes::String,
+};
+const XML: CommentSyntax = CommentSyntax {
+    line: &[],
+    block: &[("<!--", "-->")],
+    single_quotes: SingleQuotes::None,
+};
+
+/// Gets the comment syntax of a raw block's language tag.
+fn comment_syntax(language: &str) -> Option<&'static CommentSyntax> {
+    let syntax = match language.to_ascii_lowercase().as_str() {
+        "typ" | "typst" | "typc" => &TYPST,
+        "rust" | "rs" | "c" | "h" | "cpp" | "c++" | "hpp" | "cs" | "csharp" | "java" | "go"
+        | "swift" | "kotlin" | "kt" | "scala" | "zig" | "glsl" => &C_LIKE,
+        "js" | "javascript" | "jsx" | "ts" | "typescript" | "tsx" | "dart" | "php" => &JS_LIKE,
+        "python" | "py" | "sh" | "bash" | "zsh" | "shell" | "fish" | "ruby" | "rb" | "perl"
+        | "r" | "yaml" | "yml" | "toml" | "julia" | "jl" | "nix" | "make" | "makefile"
+        | "dockerfile" | "powershell" | "ps1" | "elixir" | "ex" => &HASH,
+        "lua" => &LUA,
+        "ada" => &ADA,
+        "haskell" | "hs" | "elm" => &HASKELL,
+        "sql" => &SQL,
+        "tex" | "latex" | "matlab" | "erlang" | "erl" => &PERCENT,
+        "lisp" | "clojure" | "clj" | "scheme" | "racket" | "asm" | "ini" => &SEMICOLON,
+        "css" => &CSS,
+        "html" | "xml" | "svg" => &XML,
+        _ => return None,
+    };
+
+    Some(syntax)
+}
+
+/// A comment in the body of a raw block.
+struct Comment {
+    /// The byte range of the whole comment, including its markers.
+    range: ops::Range<usize>,
+    /// The byte range of the comment's text.
+    content: ops::Range<usize>,
+    is_line: bool,
+}
+
+/// Gets the paragraphs to check inside of a raw block.
+///
+/// The whole body of blocks in a prose language is checked, while only the comments of
+/// blocks in a known programming language are checked. Other raw blocks aren't checked.
+pub fn raw_paragraphs<'a>(node: &'a SyntaxNode, config: &RawConfig) -> Vec<Paragraph<'a>> {
+    let Some(language) = node
+        .children()
+        .find(|child| child.kind() == SyntaxKind::RawLang)
+        .map(|lang| lang.text().as_str())
+    else {
+        return vec![];
+    };
+
+    // The nodes of the body along with their offset in it
+    let mut pieces = vec![];
+    let mut body = String::new();
+    for child in node.children() {
+        if matches!(child.kind(), SyntaxKind::Text | SyntaxKind::RawTrimmed) {
+            pieces.push((body.len(), child));
+            body.push_str(child.text());
+        }
+    }
+
+    // Language tags are matched case insensitively, like in `comment_syntax`
+    let is_prose = match config.prose_languages.as_ref() {
+        Some(languages) => languages
+            .iter()
+            .any(|prose| prose.eq_ignore_ascii_case(language)),
+        None => DEFAULT_PROSE_LANGUAGES
+            .iter()
+            .any(|prose| prose.eq_ignore_ascii_case(language)),
+    };
+
+    if is_prose {
+        let start = body.len() - body.trim_start().len();
+        let end = body.trim_end().len();
+        if start >= end {
+            return vec![];
+        }
+
+        let mut paragraph = Paragraph::new(ParagraphKind::Body);
+        paragraph.nodes = slices(&pieces, start..end);
+
+        return vec![paragraph];
+    }
+
+    let Some(syntax) = comment_syntax(language) else {
+        return vec![];
+    };
+    if !config.check_comments.unwrap_or(true) {
+        return vec![];
+    }
+
+    let mut paragraphs = vec![];
+    let mut current: Option<(Paragraph, Comment)> = None;
+
+    for comment in find_comments(&body, syntax) {
+        if body[comment.content.clone()].trim().is_empty() {
+            paragraphs.extend(current.take().map(|(paragraph, _)| paragraph));
+            continue;
+        }
+
+        // Line comments on consecutive lines are read as a single paragraph
+        if let Some((paragraph, previous)) = current.as_mut() {
+            let gap = &body[previous.range.end..comment.range.start];
+            let is_continued = previous.is_line
+                && comment.is_line
+                && gap.trim().is_empty()
+                && gap.matches('\n').count() == 1;
+
+            if is_continued {
+                let mut separator = slices(&pieces, previous.content.end..comment.content.start);
+                for (index, node) in separator.iter_mut().enumerate() {
+                    node.interpret_as = Some(if index == 0 { " " } else { "" }.to_string());
+                }
+
+                paragraph.nodes.append(&mut separator);
+                paragraph
+                    .nodes
+                    .append(&mut slices(&pieces, comment.content.clone()));
+                *previous = comment;
+                continue;
+            }
+        }
+
+        paragraphs.extend(current.take().map(|(paragraph, _)| paragraph));
+
+        let mut paragraph = Paragraph::new(ParagraphKind::Body);
+        paragraph.nodes = slices(&pieces, comment.content.clone());
+        current = Some((paragraph, comment));
+    }
+
+    paragraphs.extend(current.map(|(paragraph, _)| paragraph));
+
+    paragraphs
+}
+
+/// Finds the comments in some code, skipping over strings and character literals.
+fn find_comments(code: &str, syntax: &CommentSyntax) -> Vec<Comment> {
+    let mut comments = vec![];
+    let mut index = 0;
+
+    while index < code.len() {
+        let rest = &code[index..];
+
+        if let Some((open, close)) = syntax.block.iter().find(|(open, _)| rest.starts_with(open)) {
+            let content_start = index + open.len();
+            let content_end = code[content_start..]
+                .find(close)
+                .map_or(code.len(), |end| content_start + end);
+            let end = (content_end + close.len()).min(code.len());
+
+            comments.push(Comment {
+                range: index..end,
+                content: trimmed(code, content_start..content_end),
+                is_line: false,
+            });
+            index = end;
+            continue;
+        }
+
+        if let Some(marker) = syntax.line.iter().find(|marker| rest.starts_with(*marker)) {
+            let end = rest.find('\n').map_or(code.len(), |end| index + end);
+
+            // Doc comment markers such as `///` and `//!` aren't part of the text
+            let marker_chars = &code[index + marker.len()..end];
+            let content_start = end
+                - marker_chars
+                    .trim_start_matches(|char: char| marker.contains(char) || char == '!')
+                    .len();
+
+            comments.push(Comment {
+                range: index..end,
+                content: trimmed(code, content_start..end),
+                is_line: true,
+            });
+            index = end;
+            continue;
+        }
+
+        if rest.starts_with('"') {
+            index += string_length(rest);
+            continue;
+        }
+
+        if rest.starts_with('\'') {
+            let length = match syntax.single_quotes {
+                SingleQuotes::None => None,
+                SingleQuotes::String => Some(string_length(rest)),
+                SingleQuotes::Char => char_literal_length(rest),
+            };
+
+            if let Some(length) = length {
+                index += length;
+                continue;
+            }
+        }
+
+        index += rest.chars().next().map_or(1, char::len_utf8);
+    }
+
+    comments
+}
+
+/// Gets the length of the string at the start of `code`, up to and including the closing
+/// quote, which is the same as the opening quote. Strings end at the end of the line if
+/// they aren't closed.
+fn string_length(code: &str) -> usize {
+    let mut chars = code.char_indices();
+    let Some((_, quote)) = chars.next() else {
+        return 0;
+    };
+
+    let mut escaped = false;
+    chars
+        .find(|&(_, char)| {
+            let is_end = !escaped && (char == quote || char == '\n');
+            escaped = !escaped && char == '\\';
+            is_end
+        })
+        .map_or(code.len(), |(end, char)| end + char.len_utf8())
+}
+
+/// Gets the length of the character literal at the start of `code`, like `'a'` or
+/// `'\''`, or `None` if the quote doesn't start one.
+fn char_literal_length(code: &str) -> Option<usize> {
+    let mut chars = code.char_indices().skip(1);
+
+    match chars.next()? {
+        (_, '\\') => {
+            chars.next()?;
+            chars
+                .take_while(|&(_, char)| char != '\n')
+                .find(|&(_, char)| char == '\'')
+                .map(|(end, _)| end + 1)
+        }
+        (_, '\n') => None,
+        _ => chars
+            .next()
+            .filter(|&(_, char)| char == '\'')
+            .map(|(end, _)| end + 1),
+    }
+}
+
+/// Shrinks a range in `text` so it doesn't start or end with whitespace.
+fn trimmed(text: &str, range: ops::Range<usize>) -> ops::Range<usize> {
+    let inner = &text[range.clone()];
+    let start = range.start + inner.len() - inner.trim_start().len();
+
+    start..start.max(range.start + inner.trim_end().len())
+}
+
+/// Gets the parts of the body's nodes that are inside of `range`.
+fn slices<'a>(
+    pieces: &[(usize, &'a SyntaxNode)],
+    range: ops::Range<usize>,
+) -> Vec<ParagraphNode<'a>> {
+    let mut nodes = vec![];
+
+    for &(offset, node) in pieces {
+        let node_end = offset + node.text().len();
+        let start = range.start.max(offset);
+        let end = range.end.min(node_end);
+
+        if start >= end {
+            continue;
+        }
+
+        if start == offset && end == node_end {
+            nodes.push(ParagraphNode::from(node));
+        } else {
+            nodes.push(ParagraphNode::from(node).slice(start - offset..end - offset));
+        }
+    }
+
+    nodes
+}
+
+#[cfg(test)]
+mod tests {
+    use typst_syntax::{Source, SyntaxKind, SyntaxNode};
+
+    use super::{comment_syntax, find_comments, raw_paragraphs};
+    use crate::RawConfig;
+
+    /// Gets the text of the comments in some code.
+    fn comments<'a>(code: &'a str, language: &str) -> Vec<&'a str> {
+        find_comments(code, comment_syntax(language).unwrap())
+            .into_iter()
+            .map(|comment| &code[comment.content])
+            .collect()
+    }
+
+    #[test]
+    fn line_and_block_comments() {
+        assert_eq!(
+            comments("let x = 1; // A line\n/* A block */ let y = 2;", "rust"),
+            ["A line", "A block"]
+        );
+        assert_eq!(
+            comments("/// Docs\n//! Module docs", "rust"),
+            ["Docs", "Module docs"]
+        );
+    }
+
+    #[test]
+    fn double_quoted_strings() {
+        assert_eq!(
+            comments(r#"let s = "// not \" // still not"; // real"#, "rust"),
+            ["real"]
+        );
+    }
+
+    #[test]
+    fn single_quoted_strings() {
+        assert_eq!(
+            comments("x = '# not a comment'  # a comment", "python"),
+            ["a comment"]
+        );
+        assert_eq!(
+            comments("const s = '// not \\' // still not'; // real", "js"),
+            ["real"]
+        );
+    }
+
+    #[test]
+    fn char_literals() {
+        assert_eq!(
+            comments("let quote = '\"'; // after a quote", "rust"),
+            ["after a quote"]
+        );
+        assert_eq!(
+            comments("let quote = '\\''; // after an escape", "rust"),
+            ["after an escape"]
+        );
+        // Lifetimes aren't character literals
+        assert_eq!(
+            comments("fn f<'a>(s: &'a str) {} // after a lifetime", "rust"),
+            ["after a lifetime"]
+        );
+    }
+
+    #[test]
+    fn apostrophes_are_not_quotes() {
+        assert_eq!(
+            comments("#let x = [don't] // after an apostrophe", "typst"),
+            ["after an apostrophe"]
+        );
+        assert_eq!(
+            comments("(quote x) ; it's a comment", "lisp"),
+            ["it's a comment"]
+        );
+    }
+
+    #[test]
+    fn unclosed_string_ends_at_line() {
+        assert_eq!(comments("x = 'unclosed\n# comment", "python"), ["comment"]);
+    }
+
+    /// Gets the interpreted text of the paragraphs of the first raw block in some markup.
+    fn raw_texts(markup: &str, config: &RawConfig) -> Vec<String> {
+        fn find_raw(node: &SyntaxNode) -> Option<&SyntaxNode> {
+            if node.kind() == SyntaxKind::Raw {
+                return Some(node);
+            }
+
+            node.children().find_map(find_raw)
+        }
+
+        let source = Source::detached(markup);
+        let raw = find_raw(source.root()).unwrap();
+
+        raw_paragraphs(raw, config)
+            .iter()
+            .map(|paragraph| paragraph.get_interpreted_text())
+            .collect()
+    }
+
+    #[test]
+    fn prose_languages_ignore_case() {
+        let markup = "```Markdown\nSome prose.\n```";
+
+        assert_eq!(raw_texts(markup, &RawConfig::default()), ["Some prose."]);
+
+        let config = RawConfig {
+            prose_languages: Some(vec!["NOTES".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(
+            raw_texts("```notes\nSome notes.\n```", &config),
+            ["Some notes."]
+        );
+    }
+}
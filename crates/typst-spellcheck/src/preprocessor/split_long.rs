@@ -0,0 +1,207 @@
+use typst_syntax::SyntaxKind;
+
+use super::paragraph::{Paragraph, ParagraphNode};
+
+/// How suitable the position after a node is for splitting a paragraph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Boundary {
+    None,
+    Word,
+    Sentence,
+}
+
+/// Splits paragraphs longer than `max_length` into multiple paragraphs.
+///
+/// Paragraphs are split at the last sentence or line boundary that fits, falling back to the
+/// last word boundary. `Text` nodes are split into synthetic nodes that keep the span of the
+/// original node along with their byte range inside of it, so problem ranges stay correct.
+///
+/// A single word longer than `max_length` is left as its own paragraph.
+pub fn split_long(paragraphs: Vec<Paragraph>, max_length: usize) -> Vec<Paragraph> {
+    let mut output = vec![];
+
+    for paragraph in paragraphs {
+        if paragraph.get_text().0.len() <= max_length {
+            output.push(paragraph);
+            continue;
+        }
+
+        let kind = paragraph.kind;
+        let pieces: Vec<ParagraphNode> = paragraph.nodes.into_iter().flat_map(split_node).collect();
+
+        let mut start = 0;
+        while start < pieces.len() {
+            // Find how many pieces fit in this paragraph
+            let mut end = start;
+            let mut length = 0;
+            while end < pieces.len() && length + piece_length(&pieces[end]) <= max_length {
+                length += piece_length(&pieces[end]);
+                end += 1;
+            }
+
+            let split = if end == pieces.len() {
+                end
+            } else if end == start {
+                start + 1
+            } else {
+                // Split after the last occurrence of the best boundary in the pieces that fit
+                let best = (start..end)
+                    .map(|index| boundary_after(&pieces[index]))
+                    .max()
+                    .unwrap_or(Boundary::None);
+
+                match best {
+                    Boundary::None => end,
+                    _ => (start..end)
+                        .rev()
+                        .find(|&index| boundary_after(&pieces[index]) == best)
+                        .map_or(end, |index| index + 1),
+                }
+            };
+
+            let nodes: Vec<ParagraphNode> = pieces[start..split]
+                .iter()
+                .skip_while(|piece| piece.node.kind() == SyntaxKind::Space)
+                .cloned()
+                .collect();
+
+            if !nodes.is_empty() {
+                output.push(Paragraph { kind, nodes });
+            }
+
+            start = split;
+        }
+    }
+
+    output
+}
+
+fn piece_length(piece: &ParagraphNode) -> usize {
+    piece.text().len()
+}
+
+fn boundary_after(piece: &ParagraphNode) -> Boundary {
+    let text = piece.node.text();
+
+    if piece.interpret_as.is_some() {
+        return Boundary::None;
+    }
+
+    match piece.node.kind() {
+        SyntaxKind::Space if text.contains('\n') => Boundary::Sentence,
+        SyntaxKind::Space => Boundary::Word,
+        SyntaxKind::Text if text.ends_with(char::is_whitespace) => {
+            if text.trim_end().ends_with(['.', '!', '?']) {
+                Boundary::Sentence
+            } else {
+                Boundary::Word
+            }
+        }
+        _ => Boundary::None,
+    }
+}
+
+/// Splits a `Text` node into one synthetic node per word, including trailing whitespace.
+fn split_node(paragraph_node: ParagraphNode) -> Vec<ParagraphNode> {
+    let text = paragraph_node.node.text();
+
+    if paragraph_node.node.kind() != SyntaxKind::Text
+        || paragraph_node.interpret_as.is_some()
+        || !text.contains(char::is_whitespace)
+    {
+        return vec![paragraph_node];
+    }
+
+    let mut pieces = vec![];
+    let mut piece_start = 0;
+    let mut previous_whitespace = false;
+
+    for (index, char) in text.char_indices() {
+        if previous_whitespace && !char.is_whitespace() {
+            pieces.push(piece_start..index);
+            piece_start = index;
+        }
+
+        previous_whitespace = char.is_whitespace();
+    }
+    pieces.push(piece_start..text.len());
+
+    pieces
+        .into_iter()
+        .map(|range| paragraph_node.slice(range))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use typst_syntax::Source;
+
+    use super::split_long;
+    use crate::{preprocessor::preprocess, SpellcheckConfig};
+
+    /// Gets the text of each paragraph of `text` after splitting it.
+    fn split(text: &str, max_length: usize) -> Vec<String> {
+        let source = Source::detached(text);
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+
+        split_long(paragraphs, max_length)
+            .iter()
+            .map(|paragraph| paragraph.get_text().0)
+            .collect()
+    }
+
+    #[test]
+    fn short_paragraphs_are_kept() {
+        assert_eq!(split("One short paragraph.", 100), ["One short paragraph."]);
+    }
+
+    #[test]
+    fn splits_at_sentence_boundary() {
+        // The last word boundary that fits is after "second", but a sentence boundary wins
+        assert_eq!(
+            split("The first sentence. The second one.", 27),
+            ["The first sentence. ", "The second one."]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_word_boundary() {
+        assert_eq!(
+            split("A sentence without any stops in it", 16),
+            ["A sentence ", "without any ", "stops in it"]
+        );
+    }
+
+    #[test]
+    fn oversized_word_is_its_own_paragraph() {
+        assert_eq!(
+            split("Short words and incomprehensibilities here", 12),
+            ["Short words ", "and ", "incomprehensibilities ", "here"]
+        );
+    }
+
+    #[test]
+    fn pieces_map_to_source() {
+        let text = "Some first sentence. Another sentence with a mispeled word.";
+        let source = Source::detached(text);
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+
+        let paragraphs = split_long(paragraphs, 40);
+        assert_eq!(paragraphs.len(), 2);
+        let (piece_text, contributions) = paragraphs[1].get_text();
+
+        // Map the word in the second piece back to the source as a match would be
+        let offset = piece_text.find("mispeled").unwrap();
+        let contribution = contributions
+            .iter()
+            .find(|contribution| {
+                contribution.offset <= offset && offset < contribution.offset + contribution.length
+            })
+            .unwrap();
+        let start =
+            source.range(contribution.span).unwrap().start + contribution.span_offset + offset
+                - contribution.offset;
+
+        assert_eq!(&text[start..start + "mispeled".len()], "mispeled");
+    }
+}
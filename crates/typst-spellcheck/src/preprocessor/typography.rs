@@ -0,0 +1,44 @@
+use typst_syntax::{ast, SyntaxKind, SyntaxNode};
+
+/// Gets the text that typst renders for a `Shorthand`, `SmartQuote`, `Escape` or
+/// `Linebreak` node.
+///
+/// `previous` is the last character of the rendered text before the node, which decides
+/// if a smart quote is opening or closing.
+pub fn rendered_text(node: &SyntaxNode, previous: Option<char>) -> Option<String> {
+    let rendered = match node.kind() {
+        SyntaxKind::Shorthand => match node.cast::<ast::Shorthand>()?.get() {
+            // Soft hyphens are only visible when a word is broken across lines
+            '\u{00AD}' => String::new(),
+            char => char.to_string(),
+        },
+        SyntaxKind::SmartQuote => {
+            let double = node.cast::<ast::SmartQuote>()?.double();
+
+            smart_quote(double, previous).to_string()
+        }
+        SyntaxKind::Escape => node.cast::<ast::Escape>()?.get().to_string(),
+        SyntaxKind::Linebreak => "\n".to_string(),
+        _ => return None,
+    };
+
+    Some(rendered)
+}
+
+/// Gets the curly quote typst renders for a straight quote after `previous`.
+fn smart_quote(double: bool, previous: Option<char>) -> char {
+    let opening = match previous {
+        None => true,
+        Some(char) => {
+            char.is_whitespace()
+                || matches!(char, '(' | '[' | '{' | '\u{2013}' | '\u{2014}' | '‘' | '“')
+        }
+    };
+
+    match (double, opening) {
+        (true, true) => '“',
+        (true, false) => '”',
+        (false, true) => '‘',
+        (false, false) => '’',
+    }
+}
@@ -1,16 +1,38 @@
+use std::{cmp::Ordering, ops};
+
 use languagetool_rust::check::Match;
 use typst_syntax::Source;
 
 use crate::{
-    preprocessor::paragraph::NodeContribution,
-    range::{Position, Range},
+    preprocessor::paragraph::NodeContribution, range::Range, severity::Severity,
+    spellchecker::utf16_to_byte_range,
 };
 
+/// The order to sort problems in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum SortOrder {
+    /// By file and position in the file, then by rule id.
+    #[default]
+    Position,
+    /// By rule id, then by file and position.
+    Rule,
+    /// Most severe first, then by file and position.
+    Severity,
+}
+
 /// A problem reported by languagetool
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Problem {
+    /// The file the problem is in, if it isn't the checked typst file.
+    pub file: Option<String>,
     pub range: Range,
+    /// Byte offset of the problem in the file.
+    pub offset: usize,
+    /// Length of the problem in bytes.
+    pub length: usize,
     pub match_string: String,
     pub context: String,
     pub short_message: String,
@@ -18,17 +40,51 @@ pub struct Problem {
     pub replacements: Vec<String>,
     pub rule_category: String,
     pub rule_id: String,
+    /// Languagetool's issue type of the rule, e.g. `misspelling` or `style`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub issue_type: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rule_description: Option<String>,
+    /// Pages that explain the rule.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rule_urls: Vec<String>,
+    /// Readable name of the rule category, e.g. `Possible Typo`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub category_name: Option<String>,
+    /// The sentence the problem was found in, as languagetool read it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub sentence: Option<String>,
+    /// Byte offset of the match in `context`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub context_offset: Option<usize>,
+    /// Length of the match in `context` in bytes.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub context_length: Option<usize>,
+    /// The match can be ignored while the sentence is still being written, e.g. a missing
+    /// period at its end. Only known with the `unstable` feature.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub ignore_for_incomplete_sentence: Option<bool>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub severity: Severity,
+    /// The match couldn't be mapped to its exact position, so the range is the one of
+    /// the paragraph it was found in.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub unlocated: bool,
 }
 
 impl Problem {
+    /// Creates a problem from a languagetool match.
+    ///
+    /// `match_range` is the byte range of the match in the paragraph's text.
     pub fn try_from_match(
         source: &Source,
         lt_match: Match,
+        match_range: ops::Range<usize>,
         match_string: String,
         node_contributions: &[NodeContribution],
     ) -> Option<Self> {
-        let match_start = lt_match.offset;
-        let match_end = lt_match.offset + lt_match.length;
+        let match_start = match_range.start;
+        let match_end = match_range.end;
 
         // Find the contribution that contains the start of the match
         let start_contribution = node_contributions.iter().find(|contribution| {
@@ -49,44 +105,300 @@ impl Problem {
         let match_end_offset = match_end - end_contribution.offset;
 
         // Find the range of that node in document space
-        let node_start = source.range(start_contribution.span)?.start;
-        let node_end = source.range(end_contribution.span)?.start;
+        let node_start =
+            source.range(start_contribution.span)?.start + start_contribution.span_offset;
+        let node_end = source.range(end_contribution.span)?.start + end_contribution.span_offset;
 
         // because a match may start part way through a node, the start range needs to be offset by the amount
         let doc_match_start = node_start + match_start_offset;
         let doc_match_end = node_end + match_end_offset;
 
-        let range_start = Position {
-            column: source.byte_to_column(doc_match_start)? + 1,
-            line: source.byte_to_line(doc_match_start)? + 1,
-        };
+        Self::try_from_byte_range(
+            source,
+            lt_match,
+            doc_match_start..doc_match_end,
+            match_string,
+        )
+    }
+
+    /// Creates an unlocated problem from a languagetool match that couldn't be mapped,
+    /// where `paragraph_range` is the byte range of its paragraph in the source.
+    pub fn unlocated(
+        source: &Source,
+        lt_match: Match,
+        paragraph_range: ops::Range<usize>,
+        match_string: String,
+    ) -> Option<Self> {
+        let mut problem =
+            Self::try_from_byte_range(source, lt_match, paragraph_range, match_string)?;
+        problem.unlocated = true;
+
+        Some(problem)
+    }
 
-        let range_end = Position {
-            column: source.byte_to_column(doc_match_end)? + 1,
-            line: source.byte_to_line(doc_match_end)? + 1,
-        };
+    /// Creates a problem from a languagetool match at `byte_range` in the source.
+    pub fn try_from_byte_range(
+        source: &Source,
+        lt_match: Match,
+        byte_range: ops::Range<usize>,
+        match_string: String,
+    ) -> Option<Self> {
+        let offset = byte_range.start;
+        let length = byte_range.len();
+        let range = Range::from_byte_range(source, byte_range)?;
 
-        let range = Range {
-            start: range_start,
-            end: range_end,
-        };
+        let (corrected_context, context_range) = correct_context(
+            &lt_match.context.text,
+            lt_match.context.offset,
+            lt_match.context.length,
+        );
 
-        let corrected_context = lt_match
-            .context
-            .text
-            .chars()
-            .filter(|c| c != &'\r')
-            .collect::<String>();
+        let rule = lt_match.rule;
+        let severity = Severity::from_issue(&rule.issue_type, &rule.category.id);
 
         Some(Self {
+            file: None,
             range,
+            offset,
+            length,
             match_string,
             context: corrected_context,
             short_message: lt_match.short_message,
             message: lt_match.message,
             replacements: lt_match.replacements.into_iter().map(|v| v.value).collect(),
-            rule_category: lt_match.rule.category.id,
-            rule_id: lt_match.rule.id,
+            severity,
+            rule_category: rule.category.id,
+            rule_id: rule.id,
+            issue_type: non_empty(rule.issue_type),
+            rule_description: non_empty(rule.description),
+            rule_urls: rule
+                .urls
+                .unwrap_or_default()
+                .into_iter()
+                .map(|url| url.value)
+                .collect(),
+            category_name: non_empty(rule.category.name),
+            sentence: non_empty(lt_match.sentence),
+            context_offset: context_range.as_ref().map(|range| range.start),
+            context_length: context_range.map(|range| range.len()),
+            #[cfg(feature = "unstable")]
+            ignore_for_incomplete_sentence: Some(lt_match.ignore_for_incomplete_sentence),
+            #[cfg(not(feature = "unstable"))]
+            ignore_for_incomplete_sentence: None,
+            unlocated: false,
         })
     }
 }
+
+/// Sorts problems in a total order, so the result doesn't depend on the order they were
+/// found in.
+pub fn sort_problems(problems: &mut [Problem], order: SortOrder) {
+    problems.sort_by(|a, b| {
+        match order {
+            SortOrder::Position => compare_position(a, b).then_with(|| a.rule_id.cmp(&b.rule_id)),
+            SortOrder::Rule => a
+                .rule_id
+                .cmp(&b.rule_id)
+                .then_with(|| compare_position(a, b)),
+            SortOrder::Severity => b
+                .severity
+                .cmp(&a.severity)
+                .then_with(|| compare_position(a, b)),
+        }
+        .then_with(|| a.cmp(b))
+    });
+}
+
+/// Removes problems with the same file, range and rule id as another problem, and returns
+/// how many were removed. The problems are left sorted by position.
+///
+/// Unlocated problems are never removed, since all of the unlocated problems in a
+/// paragraph share its range.
+pub fn dedup_problems(problems: &mut Vec<Problem>) -> usize {
+    let count = problems.len();
+
+    sort_problems(problems, SortOrder::Position);
+    problems.dedup_by(|a, b| {
+        !a.unlocated
+            && !b.unlocated
+            && a.file == b.file
+            && a.range == b.range
+            && a.rule_id == b.rule_id
+    });
+
+    count - problems.len()
+}
+
+/// Removes the carriage returns from a match context, and converts languagetool's range of
+/// the match in it, which is in UTF-16 units, to a byte range in the corrected context.
+fn correct_context(
+    text: &str,
+    offset: usize,
+    length: usize,
+) -> (String, Option<ops::Range<usize>>) {
+    let range = utf16_to_byte_range(text, offset, length).map(|range| {
+        let removed_before = text[..range.start].matches('\r').count();
+        let removed_inside = text[range.clone()].matches('\r').count();
+
+        range.start - removed_before..range.end - removed_before - removed_inside
+    });
+
+    (text.replace('\r', ""), range)
+}
+
+fn non_empty(text: String) -> Option<String> {
+    (!text.is_empty()).then_some(text)
+}
+
+fn compare_position(a: &Problem, b: &Problem) -> Ordering {
+    a.file
+        .cmp(&b.file)
+        .then_with(|| a.range.cmp(&b.range))
+        .then_with(|| a.offset.cmp(&b.offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{correct_context, dedup_problems, sort_problems, Problem, SortOrder};
+    use crate::{
+        range::{Position, Range},
+        severity::Severity,
+    };
+
+    fn problem(file: Option<&str>, line: usize, rule_id: &str, severity: Severity) -> Problem {
+        let position = |column| Position { line, column };
+
+        Problem {
+            file: file.map(str::to_string),
+            range: Range {
+                start: position(1),
+                end: position(4),
+            },
+            offset: line * 100,
+            length: 3,
+            match_string: "teh".to_string(),
+            rule_id: rule_id.to_string(),
+            severity,
+            ..Default::default()
+        }
+    }
+
+    /// Gets the line and rule id of each problem.
+    fn order(problems: &[Problem]) -> Vec<(usize, &str)> {
+        problems
+            .iter()
+            .map(|problem| (problem.range.start.line, problem.rule_id.as_str()))
+            .collect()
+    }
+
+    fn unsorted() -> Vec<Problem> {
+        vec![
+            problem(None, 3, "B_RULE", Severity::Hint),
+            problem(None, 1, "C_RULE", Severity::Warning),
+            problem(Some("refs.bib"), 1, "A_RULE", Severity::Error),
+            problem(None, 2, "A_RULE", Severity::Error),
+        ]
+    }
+
+    #[test]
+    fn sort_by_position() {
+        let mut problems = unsorted();
+        sort_problems(&mut problems, SortOrder::Position);
+
+        // Problems in the checked file come before problems in other files
+        assert_eq!(
+            order(&problems),
+            vec![(1, "C_RULE"), (2, "A_RULE"), (3, "B_RULE"), (1, "A_RULE")]
+        );
+    }
+
+    #[test]
+    fn sort_by_rule() {
+        let mut problems = unsorted();
+        sort_problems(&mut problems, SortOrder::Rule);
+
+        assert_eq!(
+            order(&problems),
+            vec![(2, "A_RULE"), (1, "A_RULE"), (3, "B_RULE"), (1, "C_RULE")]
+        );
+    }
+
+    #[test]
+    fn sort_by_severity() {
+        let mut problems = unsorted();
+        sort_problems(&mut problems, SortOrder::Severity);
+
+        assert_eq!(
+            order(&problems),
+            vec![(2, "A_RULE"), (1, "A_RULE"), (1, "C_RULE"), (3, "B_RULE")]
+        );
+    }
+
+    #[test]
+    fn sort_is_independent_of_input_order() {
+        let mut problems = unsorted();
+        let mut reversed: Vec<Problem> = unsorted().into_iter().rev().collect();
+
+        for order in [SortOrder::Position, SortOrder::Rule, SortOrder::Severity] {
+            sort_problems(&mut problems, order);
+            sort_problems(&mut reversed, order);
+            assert_eq!(problems, reversed);
+        }
+    }
+
+    #[test]
+    fn dedup_same_range_and_rule() {
+        let mut problems = vec![
+            problem(None, 1, "A_RULE", Severity::Error),
+            problem(None, 2, "A_RULE", Severity::Error),
+            problem(None, 1, "A_RULE", Severity::Error),
+            problem(None, 1, "B_RULE", Severity::Error),
+            problem(Some("refs.bib"), 1, "A_RULE", Severity::Error),
+        ];
+
+        assert_eq!(dedup_problems(&mut problems), 1);
+        assert_eq!(
+            order(&problems),
+            vec![(1, "A_RULE"), (1, "B_RULE"), (2, "A_RULE"), (1, "A_RULE")]
+        );
+    }
+
+    #[test]
+    fn dedup_keeps_unlocated() {
+        let mut unlocated = problem(None, 1, "A_RULE", Severity::Error);
+        unlocated.unlocated = true;
+
+        let mut other = unlocated.clone();
+        other.match_string = "wrold".to_string();
+
+        let mut problems = vec![unlocated.clone(), other, unlocated];
+
+        assert_eq!(dedup_problems(&mut problems), 0);
+        assert_eq!(problems.len(), 3);
+    }
+
+    #[test]
+    fn context_range_is_in_bytes() {
+        // "é" is one UTF-16 unit but two bytes, and "𝔸" is two units and four bytes
+        let (context, range) = correct_context("é𝔸 teh word", 4, 3);
+
+        assert_eq!(context, "é𝔸 teh word");
+        assert_eq!(&context[range.unwrap()], "teh");
+    }
+
+    #[test]
+    fn context_without_carriage_returns() {
+        // A carriage return before the match and one inside of it
+        let (context, range) = correct_context("Line\r\nteh\r\nword end", 6, 9);
+
+        assert_eq!(context, "Line\nteh\nword end");
+        assert_eq!(&context[range.unwrap()], "teh\nword");
+    }
+
+    #[test]
+    fn context_range_outside_of_text() {
+        let (_, range) = correct_context("teh", 2, 5);
+
+        assert_eq!(range, None);
+    }
+}
@@ -1,19 +1,41 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+use typst_syntax::Source;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Range {
     pub start: Position,
     pub end: Position,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Position {
     pub line: usize,
     pub column: usize,
 }
 
+impl Range {
+    /// Converts a byte range in the source into a one-based line and column range.
+    pub fn from_byte_range(source: &Source, range: std::ops::Range<usize>) -> Option<Self> {
+        Some(Self {
+            start: Position::from_byte(source, range.start)?,
+            end: Position::from_byte(source, range.end)?,
+        })
+    }
+}
+
+impl Position {
+    /// Converts a byte index in the source into a one-based line and column position.
+    pub fn from_byte(source: &Source, byte: usize) -> Option<Self> {
+        Some(Self {
+            column: source.byte_to_column(byte)? + 1,
+            line: source.byte_to_line(byte)? + 1,
+        })
+    }
+}
+
 impl Display for Range {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if self.start.line == self.end.line {
@@ -0,0 +1,116 @@
+use std::fmt::Display;
+
+/// How important a problem is, from least to most severe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Severity {
+    Hint,
+    Info,
+    #[default]
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// Derives the severity of a languagetool match from its rule's issue type and
+    /// category id.
+    ///
+    /// Misspellings and grammar mistakes are errors, style suggestions are infos and
+    /// suggestions from purely stylistic categories are hints. Everything else, like
+    /// typography and punctuation, is a warning.
+    pub fn from_issue(issue_type: &str, category: &str) -> Self {
+        match category {
+            "TYPOS" | "GRAMMAR" => return Self::Error,
+            "STYLE" | "REDUNDANCY" | "PLAIN_ENGLISH" | "CREATIVE_WRITING" | "TEXT_ANALYSIS" => {
+                return Self::Hint
+            }
+            _ => {}
+        }
+
+        match issue_type {
+            "misspelling" | "grammar" => Self::Error,
+            "style" | "register" => Self::Info,
+            _ => Self::Warning,
+        }
+    }
+
+    /// The `DiagnosticSeverity` of the language server protocol.
+    pub fn lsp_severity(self) -> u8 {
+        match self {
+            Self::Error => 1,
+            Self::Warning => 2,
+            Self::Info => 3,
+            Self::Hint => 4,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Info => "info",
+            Self::Hint => "hint",
+        }
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Severity;
+
+    #[test]
+    fn severity_from_category() {
+        // Categories take precedence over the issue type
+        assert_eq!(Severity::from_issue("style", "TYPOS"), Severity::Error);
+        assert_eq!(
+            Severity::from_issue("misspelling", "GRAMMAR"),
+            Severity::Error
+        );
+        assert_eq!(Severity::from_issue("grammar", "STYLE"), Severity::Hint);
+        assert_eq!(Severity::from_issue("style", "REDUNDANCY"), Severity::Hint);
+        assert_eq!(
+            Severity::from_issue("style", "PLAIN_ENGLISH"),
+            Severity::Hint
+        );
+        assert_eq!(
+            Severity::from_issue("style", "CREATIVE_WRITING"),
+            Severity::Hint
+        );
+        assert_eq!(
+            Severity::from_issue("style", "TEXT_ANALYSIS"),
+            Severity::Hint
+        );
+    }
+
+    #[test]
+    fn severity_from_issue_type() {
+        assert_eq!(
+            Severity::from_issue("misspelling", "OTHER"),
+            Severity::Error
+        );
+        assert_eq!(Severity::from_issue("grammar", "OTHER"), Severity::Error);
+        assert_eq!(Severity::from_issue("style", "OTHER"), Severity::Info);
+        assert_eq!(Severity::from_issue("register", "OTHER"), Severity::Info);
+        assert_eq!(
+            Severity::from_issue("typographical", "TYPOGRAPHY"),
+            Severity::Warning
+        );
+        assert_eq!(Severity::from_issue("", ""), Severity::Warning);
+    }
+
+    #[test]
+    fn severities_are_ordered() {
+        assert!(Severity::Hint < Severity::Info);
+        assert!(Severity::Info < Severity::Warning);
+        assert!(Severity::Warning < Severity::Error);
+        assert_eq!(Severity::Error.lsp_severity(), 1);
+        assert_eq!(Severity::Hint.lsp_severity(), 4);
+    }
+}
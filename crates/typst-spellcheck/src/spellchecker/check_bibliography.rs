@@ -0,0 +1,218 @@
+use std::{ops, path::Path, sync::Arc};
+
+use languagetool_rust::check::{Data, DataAnnotation, Match};
+use typst_syntax::{FileId, Source, VirtualPath};
+
+use crate::{
+    bibliography::{find_bibliographies, parse_fields, Field, Format},
+    preprocessor::paragraph::ParagraphKind,
+    problem::Problem,
+    range::Range,
+};
+
+use super::{
+    check_chunks::Chunk, check_file::Error, check_source::CheckOptions,
+    failed_paragraph::FailedParagraph, metadata::Metadata, Spellchecker,
+};
+
+/// The fields of a bibliography file that are checked in a single request.
+struct BibliographyChunk {
+    /// The path of the bibliography file, as it is shown.
+    path: String,
+    source: Arc<Source>,
+    annotations: Vec<DataAnnotation>,
+    /// The text that languagetool offsets are relative to, including markup.
+    text: String,
+    /// The offset in `text` of each text segment, and its byte range in the file.
+    segments: Vec<(usize, ops::Range<usize>)>,
+}
+
+impl BibliographyChunk {
+    fn new(path: String, source: Arc<Source>) -> Self {
+        Self {
+            path,
+            source,
+            annotations: vec![],
+            text: String::new(),
+            segments: vec![],
+        }
+    }
+
+    fn push(&mut self, field: &Field) {
+        let contents = self.source.text();
+
+        if !self.text.is_empty() {
+            self.annotations
+                .push(DataAnnotation::new_interpreted_markup(
+                    "\n\n".to_string(),
+                    "\n\n".to_string(),
+                ));
+            self.text.push_str("\n\n");
+        }
+
+        for segment in &field.segments {
+            let segment_text = &contents[segment.range.clone()];
+
+            match &segment.interpret_as {
+                Some(interpret_as) => {
+                    self.annotations
+                        .push(DataAnnotation::new_interpreted_markup(
+                            segment_text.to_string(),
+                            interpret_as.clone(),
+                        ))
+                }
+                None => self
+                    .annotations
+                    .push(DataAnnotation::new_text(segment_text.to_string())),
+            }
+
+            self.segments.push((self.text.len(), segment.range.clone()));
+            self.text.push_str(segment_text);
+        }
+    }
+
+    /// Maps a byte range in the chunk's text to a byte range in the file.
+    fn file_range(&self, range: ops::Range<usize>) -> Option<ops::Range<usize>> {
+        let find = |offset: usize, inclusive_end: bool| {
+            self.segments.iter().find_map(|(start, file_range)| {
+                let end = start + file_range.len();
+                let contains = offset >= *start && (offset < end || inclusive_end && offset == end);
+
+                contains.then(|| file_range.start + offset - start)
+            })
+        };
+
+        Some(find(range.start, false)?..find(range.end, true)?)
+    }
+}
+
+impl Chunk for BibliographyChunk {
+    fn data(&self) -> Data {
+        self.annotations.iter().cloned().collect()
+    }
+
+    fn kind(&self) -> ParagraphKind {
+        ParagraphKind::Metadata
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn source(&self) -> &Source {
+        &self.source
+    }
+
+    fn file(&self) -> Option<String> {
+        Some(self.path.clone())
+    }
+
+    fn problem(
+        &self,
+        lt_match: Match,
+        range: ops::Range<usize>,
+        match_text: String,
+    ) -> Option<Problem> {
+        Problem::try_from_byte_range(&self.source, lt_match, self.file_range(range)?, match_text)
+    }
+
+    /// Gets the byte range in the file from the first to the last segment of the chunk.
+    fn source_range(&self) -> Option<ops::Range<usize>> {
+        let (_, first) = self.segments.first()?;
+        let (_, last) = self.segments.last()?;
+
+        Some(first.start..last.end)
+    }
+}
+
+impl Spellchecker {
+    /// Checks the titles of the entries in the bibliography files used by the document.
+    ///
+    /// Bibliography paths are resolved relative to the directory of the checked source,
+    /// or to the root if they start with `/`. Request and match statistics are added to
+    /// `metadata`.
+    pub(super) async fn check_bibliographies(
+        &self,
+        source: &Source,
+        options: &CheckOptions,
+        max_length: usize,
+        metadata: &mut Metadata,
+    ) -> (Vec<Problem>, Vec<FailedParagraph>) {
+        let mut failed_paragraphs = vec![];
+        let mut chunks = vec![];
+
+        let root = options.root.as_deref().unwrap_or(Path::new(""));
+        let source_path = source.id().vpath().as_rootless_path();
+        let directory = root.join(source_path.parent().unwrap_or(Path::new("")));
+
+        for (bibliography_path, span) in find_bibliographies(source.root()) {
+            let path = match bibliography_path.strip_prefix('/') {
+                Some(rooted) => root.join(rooted),
+                None => directory.join(&bibliography_path),
+            };
+            let display_path = path.to_string_lossy().to_string();
+
+            let Some(format) = Format::from_path(&path) else {
+                log::warn!("Unsupported bibliography format: {}", display_path);
+                continue;
+            };
+
+            let contents = match std::fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(error) => {
+                    failed_paragraphs.push(FailedParagraph {
+                        file: None,
+                        range: source
+                            .range(span)
+                            .and_then(|range| Range::from_byte_range(source, range)),
+                        error: Error::ReadBibliography(display_path, error),
+                    });
+                    continue;
+                }
+            };
+
+            let bibliography_source = Arc::new(Source::new(
+                FileId::new(None, VirtualPath::new(&path)),
+                contents,
+            ));
+
+            let mut file_chunks = vec![];
+            let mut chunk =
+                BibliographyChunk::new(display_path.clone(), Arc::clone(&bibliography_source));
+            let (fields, errors) = parse_fields(bibliography_source.text(), format);
+            for error in errors {
+                failed_paragraphs.push(FailedParagraph {
+                    file: Some(display_path.clone()),
+                    range: Range::from_byte_range(&bibliography_source, error.range.clone()),
+                    error: Error::ParseBibliography(display_path.clone(), error),
+                });
+            }
+
+            for field in fields {
+                let field_length: usize = field.segments.iter().map(|s| s.range.len()).sum();
+
+                if !chunk.text.is_empty() && chunk.text.len() + field_length > max_length {
+                    let next = BibliographyChunk::new(
+                        display_path.clone(),
+                        Arc::clone(&bibliography_source),
+                    );
+                    file_chunks.push(std::mem::replace(&mut chunk, next));
+                }
+
+                chunk.push(&field);
+            }
+            file_chunks.push(chunk);
+
+            chunks.extend(
+                file_chunks
+                    .into_iter()
+                    .filter(|chunk| !chunk.text.is_empty()),
+            );
+        }
+
+        let (problems, mut request_failures) = self.check_chunks(&chunks, options, metadata).await;
+        failed_paragraphs.append(&mut request_failures);
+
+        (problems, failed_paragraphs)
+    }
+}
@@ -0,0 +1,245 @@
+use std::{ops, sync::Arc, time::Instant};
+
+use futures::{stream::FuturesUnordered, StreamExt};
+use languagetool_rust::{
+    check::{Data, Match},
+    CheckResponse,
+};
+use typst_syntax::Source;
+
+use crate::{preprocessor::paragraph::ParagraphKind, problem::Problem, range::Range};
+
+use super::{
+    check_file::Error, check_source::CheckOptions, failed_paragraph::FailedParagraph,
+    metadata::Metadata, utf16_to_byte_range, Spellchecker,
+};
+
+/// Text that is checked in a single languagetool request, and that the matches of the
+/// request are mapped back from.
+pub(super) trait Chunk {
+    /// The annotated text that is sent to languagetool.
+    fn data(&self) -> Data;
+
+    /// The kind of paragraph whose rules the chunk is checked with.
+    fn kind(&self) -> ParagraphKind;
+
+    /// The text that languagetool's match offsets refer to.
+    fn text(&self) -> &str;
+
+    /// The source that problems are located in.
+    fn source(&self) -> &Source;
+
+    /// The file the chunk is from, if it isn't the checked source.
+    fn file(&self) -> Option<String> {
+        None
+    }
+
+    /// Gets the text of a byte range of [`Chunk::text`] as languagetool read it.
+    fn match_text(&self, range: ops::Range<usize>) -> String {
+        self.text()[range].to_string()
+    }
+
+    /// Makes a problem at the exact position of a match in the source, where `range` is
+    /// the byte range of the match in [`Chunk::text`].
+    fn problem(
+        &self,
+        lt_match: Match,
+        range: ops::Range<usize>,
+        match_text: String,
+    ) -> Option<Problem>;
+
+    /// The byte range in the source that the chunk covers, where matches that can't be
+    /// located and failed requests are reported.
+    fn source_range(&self) -> Option<ops::Range<usize>>;
+
+    /// Called with each response before its matches are mapped.
+    fn debug_response(&self, _response: &CheckResponse) {}
+}
+
+impl Spellchecker {
+    /// Checks each chunk in its own request, and maps the matches of the responses to
+    /// problems.
+    ///
+    /// Requests that fail are returned as failed paragraphs. Request statistics, mapping
+    /// time and the matches that aren't reported are added to `metadata`.
+    pub(super) async fn check_chunks<C: Chunk>(
+        &self,
+        chunks: &[C],
+        options: &CheckOptions,
+        metadata: &mut Metadata,
+    ) -> (Vec<Problem>, Vec<FailedParagraph>) {
+        let mut tasks: FuturesUnordered<_> = chunks
+            .iter()
+            .map(|chunk| {
+                let request = self.request(chunk.data(), chunk.kind(), options);
+
+                let client = Arc::clone(&self.client);
+
+                async move {
+                    let start = Instant::now();
+                    let result = client.check(&request).await;
+
+                    (result, start.elapsed(), chunk)
+                }
+            })
+            .collect();
+
+        let mut problems = vec![];
+        let mut failed_paragraphs = vec![];
+
+        while let Some((result, latency, chunk)) = tasks.next().await {
+            metadata.request_statistics.record(latency);
+
+            // A failed request only loses the problems for its own chunk
+            let response = match result {
+                Ok(response) => response,
+                Err(error) => {
+                    failed_paragraphs.push(FailedParagraph {
+                        file: chunk.file(),
+                        range: chunk
+                            .source_range()
+                            .and_then(|range| Range::from_byte_range(chunk.source(), range)),
+                        error: Error::LanguageTool(error),
+                    });
+                    continue;
+                }
+            };
+
+            chunk.debug_response(&response);
+
+            let mapping_start = Instant::now();
+            for lt_match in response.matches {
+                let Some(match_range) =
+                    utf16_to_byte_range(chunk.text(), lt_match.offset, lt_match.length)
+                else {
+                    log::warn!("Match is outside of the chunk text:\n{:?}", lt_match);
+                    metadata.dropped_matches.unmapped += 1;
+                    continue;
+                };
+                let match_text = chunk.match_text(match_range.clone());
+
+                // Check if match is an ignore word
+                if let Some(ignore_words) = self.spellcheck_config.ignore_words.as_ref() {
+                    if ignore_words.contains(&match_text) {
+                        metadata.dropped_matches.ignored_words += 1;
+                        continue;
+                    }
+                }
+
+                let maybe_problem =
+                    chunk.problem(lt_match.clone(), match_range, match_text.clone());
+
+                // Matches that can't be located are still reported, at their chunk
+                let maybe_problem = maybe_problem.or_else(|| {
+                    let problem = Problem::unlocated(
+                        chunk.source(),
+                        lt_match.clone(),
+                        chunk.source_range()?,
+                        match_text,
+                    )?;
+                    log::debug!("Failed to locate match in chunk:\n{:?}", lt_match);
+                    metadata.unlocated_count += 1;
+
+                    Some(problem)
+                });
+
+                match maybe_problem {
+                    Some(mut problem) => {
+                        problem.file = chunk.file();
+                        problems.push(problem);
+                    }
+                    None => {
+                        log::warn!("Failed to make problem for match:\n{:?}", lt_match);
+                        metadata.dropped_matches.unmapped += 1;
+                    }
+                }
+            }
+            metadata.timings.mapping += mapping_start.elapsed();
+        }
+
+        (problems, failed_paragraphs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops;
+
+    use languagetool_rust::check::{Data, DataAnnotation, Match};
+    use typst_syntax::Source;
+
+    use super::Chunk;
+    use crate::{
+        preprocessor::paragraph::ParagraphKind,
+        problem::Problem,
+        spellchecker::{check_source::CheckOptions, metadata::Metadata, test_server::TestServer},
+        SpellcheckConfig,
+    };
+
+    /// A chunk of plain text in its own source.
+    struct TextChunk {
+        source: Source,
+    }
+
+    impl Chunk for TextChunk {
+        fn data(&self) -> Data {
+            Data::from_iter([DataAnnotation::new_text(self.source.text().to_string())])
+        }
+
+        fn kind(&self) -> ParagraphKind {
+            ParagraphKind::Body
+        }
+
+        fn text(&self) -> &str {
+            self.source.text()
+        }
+
+        fn source(&self) -> &Source {
+            &self.source
+        }
+
+        fn problem(
+            &self,
+            lt_match: Match,
+            range: ops::Range<usize>,
+            match_text: String,
+        ) -> Option<Problem> {
+            Problem::try_from_byte_range(&self.source, lt_match, range, match_text)
+        }
+
+        fn source_range(&self) -> Option<ops::Range<usize>> {
+            Some(0..self.source.text().len())
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_requests_keep_other_problems() {
+        let server = TestServer::start(&["teh"]);
+        let spellchecker = server.spellchecker(SpellcheckConfig::default());
+
+        let chunks: Vec<TextChunk> = ["First teh chunk.", "A FAIL chunk\nwith teh.", "Last teh."]
+            .into_iter()
+            .map(|text| TextChunk {
+                source: Source::detached(text),
+            })
+            .collect();
+
+        let mut metadata = Metadata::default();
+        let (problems, failed) = spellchecker
+            .check_chunks(&chunks, &CheckOptions::default(), &mut metadata)
+            .await;
+
+        let mut found: Vec<(usize, usize)> = problems
+            .iter()
+            .map(|problem| (problem.offset, problem.length))
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![(5, 3), (6, 3)]);
+
+        // The failed chunk is reported with its whole range
+        assert_eq!(failed.len(), 1);
+        let range = failed[0].range.unwrap();
+        assert_eq!((range.start.line, range.end.line), (1, 2));
+        assert_eq!(metadata.request_statistics.request_count, 3);
+    }
+}
@@ -1,137 +1,59 @@
-use std::{sync::Arc, time::Instant};
+use std::{io, path::Path, time::Instant};
 
-use futures::{stream::FuturesUnordered, StreamExt};
-use languagetool_rust::{check::Level, CheckRequest};
 use thiserror::Error;
 use typst_syntax::{FileId, Source, VirtualPath};
 
-use crate::{
-    preprocessor::{merge_short::merge_short, preprocess},
-    problem::Problem,
-    word_count::count_words_naive,
-};
+use crate::{bibliography::ParseError, problem::Problem};
 
 use super::{
-    debug::{
-        debug_paragraphs, debug_problems, debug_response, debug_syntax_tree, setup_debug_file,
-    },
-    metadata::Metadata,
-    Spellchecker,
+    check_source::CheckOptions, failed_paragraph::FailedParagraph, metadata::Metadata, Spellchecker,
 };
 
 impl Spellchecker {
+    /// Checks the contents of a typst file.
+    ///
+    /// Bibliography files are resolved relative to the file's directory, unless the
+    /// options set another root.
     pub async fn check_file(
         &self,
         file_path: &str,
         file_contents: String,
-        debug: bool,
-    ) -> Result<(Vec<Problem>, Metadata), Error> {
-        if debug {
-            setup_debug_file();
-        }
-
+        options: &CheckOptions,
+    ) -> Result<(Vec<Problem>, Vec<FailedParagraph>, Metadata), Error> {
+        let path = Path::new(file_path);
+        let file_name = path.file_name().map_or(path, Path::new);
+        let parse_start = Instant::now();
         let source = Source::new(
-            FileId::new(None, VirtualPath::new(file_path)),
+            FileId::new(None, VirtualPath::new(file_name)),
             file_contents,
         );
+        let parse_time = parse_start.elapsed();
 
-        if debug {
-            debug_syntax_tree(source.root());
-        }
-
-        let paragraphs = preprocess(source.root());
-        let paragraphs = merge_short(paragraphs, 512);
-
-        if debug {
-            debug_paragraphs(&paragraphs);
-        }
-
-        let mut tasks: FuturesUnordered<_> = paragraphs
-            .iter()
-            .map(|paragraph| {
-                let (text, contributions) = paragraph.get_text();
-                let mut request = CheckRequest::default()
-                    .with_text(text.clone())
-                    .with_language(self.languagetool_config.language.clone());
-
-                if self.languagetool_config.picky.unwrap_or(false) {
-                    request.level = Level::Picky
-                }
-
-                request
-                    .disabled_rules
-                    .clone_from(&self.languagetool_config.disabled_rules);
-                request
-                    .disabled_categories
-                    .clone_from(&self.languagetool_config.disabled_categories);
-
-                let client = Arc::clone(&self.client);
-
-                async move { (client.check(&request).await, paragraph, text, contributions) }
-            })
-            .collect();
-
-        let mut problems = vec![];
-
-        let req_start = Instant::now();
-        while let Some((result, paragraph, text, node_contributions)) = tasks.next().await {
-            let response = result?;
-
-            if debug {
-                debug_response(&response, paragraph, &text, &node_contributions);
-            }
-
-            for lt_match in response.matches {
-                let match_text = &text[lt_match.offset..(lt_match.offset + lt_match.length)];
-
-                // Check if match is an ignore word
-                if let Some(ignore_words) = self.spellcheck_config.ignore_words.as_ref() {
-                    if ignore_words.contains(&match_text.to_string()) {
-                        continue;
-                    }
-                }
-
-                let maybe_problem = Problem::try_from_match(
-                    &source,
-                    lt_match.clone(),
-                    match_text.to_string(),
-                    &node_contributions,
-                );
-
-                match maybe_problem {
-                    Some(problem) => problems.push(problem),
-                    None => {
-                        log::warn!("Failed to make problem for match:\n{:?}", lt_match);
-                    }
-                }
-            }
+        let mut options = options.clone();
+        if options.root.is_none() {
+            options.root = Some(path.parent().unwrap_or(Path::new("")).to_path_buf());
         }
-        let req_end = Instant::now();
-
-        let text = paragraphs
-            .iter()
-            .map(|p| p.get_text().0)
-            .collect::<Vec<_>>()
-            .join(" ");
 
-        let word_count = count_words_naive(&text);
+        let (problems, failed_paragraphs, mut metadata) =
+            self.check_source(&source, &options).await?;
+        metadata.timings.parse = parse_time;
 
-        let metadata = Metadata {
-            word_count,
-            languagetool_request_time: req_end.duration_since(req_start),
-            paragraph_count: paragraphs.len(),
-        };
-
-        if debug {
-            debug_problems(&problems);
-        }
-
-        Ok((problems, metadata))
+        Ok((problems, failed_paragraphs, metadata))
     }
 }
 
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("Failed to check file with languagetool.\n{0}")]
+    #[error("Failed to check paragraph with languagetool.\n{0}")]
     LanguageTool(#[from] languagetool_rust::error::Error),
+
+    #[error("Failed to read bibliography file '{0}'.\n{1}")]
+    ReadBibliography(String, #[source] io::Error),
+
+    #[error("Failed to parse bibliography file '{0}'.\n{1}")]
+    ParseBibliography(String, #[source] ParseError),
+
+    #[cfg(feature = "world")]
+    #[error("Failed to compile the document.\n{0}")]
+    Compile(String),
 }
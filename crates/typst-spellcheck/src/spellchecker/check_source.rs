@@ -0,0 +1,352 @@
+use std::{
+    ops,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use languagetool_rust::{
+    check::{Data, Match},
+    CheckResponse,
+};
+use typst_syntax::Source;
+
+use crate::{
+    preprocessor::{
+        merge_short::merge_short,
+        paragraph::{interpreted_range, NodeContribution, Paragraph, ParagraphKind},
+        preprocess,
+        split_long::split_long,
+    },
+    problem::{dedup_problems, Problem},
+    word_count::count_words_naive,
+    DEFAULT_MIN_CHUNK_LENGTH,
+};
+
+use super::{
+    check_chunks::Chunk,
+    check_file::Error,
+    debug::{
+        debug_paragraphs, debug_problems, debug_response, debug_syntax_tree, setup_debug_file,
+    },
+    failed_paragraph::FailedParagraph,
+    metadata::{ChunkStatistics, Metadata, ProblemCounts},
+    Spellchecker,
+};
+
+/// A paragraph of a typst source that is checked in a single request.
+struct ParagraphChunk<'a> {
+    source: &'a Source,
+    paragraph: &'a Paragraph<'a>,
+    separate_blocks: bool,
+    debug: bool,
+    text: String,
+    node_contributions: Vec<NodeContribution>,
+    interpreted_text: String,
+}
+
+impl<'a> ParagraphChunk<'a> {
+    fn new(
+        source: &'a Source,
+        paragraph: &'a Paragraph<'a>,
+        separate_blocks: bool,
+        debug: bool,
+    ) -> Self {
+        let (text, node_contributions) = paragraph.get_text();
+
+        Self {
+            source,
+            paragraph,
+            separate_blocks,
+            debug,
+            text,
+            node_contributions,
+            interpreted_text: paragraph.get_interpreted_text(),
+        }
+    }
+}
+
+impl Chunk for ParagraphChunk<'_> {
+    fn data(&self) -> Data {
+        self.paragraph.get_data(self.separate_blocks)
+    }
+
+    fn kind(&self) -> ParagraphKind {
+        self.paragraph.kind
+    }
+
+    fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn source(&self) -> &Source {
+        self.source
+    }
+
+    /// Gets the match as languagetool read it, with markup interpreted.
+    fn match_text(&self, range: ops::Range<usize>) -> String {
+        interpreted_range(&self.node_contributions, range.clone()).map_or_else(
+            || self.text[range].to_string(),
+            |range| self.interpreted_text[range].to_string(),
+        )
+    }
+
+    fn problem(
+        &self,
+        lt_match: Match,
+        range: ops::Range<usize>,
+        match_text: String,
+    ) -> Option<Problem> {
+        Problem::try_from_match(
+            self.source,
+            lt_match,
+            range,
+            match_text,
+            &self.node_contributions,
+        )
+    }
+
+    fn source_range(&self) -> Option<ops::Range<usize>> {
+        self.paragraph.byte_range(self.source)
+    }
+
+    fn debug_response(&self, response: &CheckResponse) {
+        if self.debug {
+            debug_response(
+                response,
+                self.paragraph,
+                &self.text,
+                &self.node_contributions,
+            );
+        }
+    }
+}
+
+/// Options for a single check, on top of the spellchecker's config.
+#[derive(Debug, Clone, Default)]
+pub struct CheckOptions {
+    /// Write the intermediate steps to a debug file.
+    pub debug: bool,
+    /// Check with this language instead of the configured one.
+    pub language: Option<String>,
+    /// The directory that the source's path is relative to, which bibliography files are
+    /// resolved from. Defaults to the current directory.
+    pub root: Option<PathBuf>,
+}
+
+impl CheckOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn with_root(mut self, root: impl AsRef<Path>) -> Self {
+        self.root = Some(root.as_ref().to_path_buf());
+        self
+    }
+}
+
+impl Spellchecker {
+    /// Checks some typst text that isn't in a file.
+    pub async fn check_text(
+        &self,
+        text: &str,
+        options: &CheckOptions,
+    ) -> Result<(Vec<Problem>, Vec<FailedParagraph>, Metadata), Error> {
+        let parse_start = Instant::now();
+        let source = Source::detached(text);
+        let parse_time = parse_start.elapsed();
+
+        let (problems, failed_paragraphs, mut metadata) =
+            self.check_source(&source, options).await?;
+        metadata.timings.parse = parse_time;
+
+        Ok((problems, failed_paragraphs, metadata))
+    }
+
+    /// Checks a typst source, e.g. one that is held by a typst `World`.
+    ///
+    /// Problems are located both by line and column and by byte offset in the source.
+    pub async fn check_source(
+        &self,
+        source: &Source,
+        options: &CheckOptions,
+    ) -> Result<(Vec<Problem>, Vec<FailedParagraph>, Metadata), Error> {
+        let debug = options.debug;
+        if debug {
+            setup_debug_file();
+            debug_syntax_tree(source.root());
+        }
+
+        let chunking = &self.spellcheck_config.chunking;
+        let min_length = chunking.min_length.unwrap_or(DEFAULT_MIN_CHUNK_LENGTH);
+        let max_length = self.max_chunk_length();
+        let merge_across_headings = chunking.merge_across_headings.unwrap_or(true);
+        let separate_blocks = chunking.separate_blocks.unwrap_or(false);
+
+        let mut metadata = Metadata::default();
+
+        let preprocess_start = Instant::now();
+        let paragraphs = preprocess(source.root(), &self.spellcheck_config);
+        let source_paragraph_count = paragraphs.len();
+        let paragraphs = split_long(paragraphs, max_length);
+        let paragraphs = merge_short(paragraphs, min_length, max_length, merge_across_headings);
+        metadata.timings.preprocess = preprocess_start.elapsed();
+
+        if debug {
+            debug_paragraphs(&paragraphs);
+        }
+
+        let chunks: Vec<_> = paragraphs
+            .iter()
+            .map(|paragraph| ParagraphChunk::new(source, paragraph, separate_blocks, debug))
+            .collect();
+
+        let req_start = Instant::now();
+        let (mut problems, mut failed_paragraphs) =
+            self.check_chunks(&chunks, options, &mut metadata).await;
+
+        if self.spellcheck_config.check_bibliography.unwrap_or(false) {
+            let (mut bibliography_problems, mut bibliography_failures) = self
+                .check_bibliographies(source, options, max_length, &mut metadata)
+                .await;
+
+            problems.append(&mut bibliography_problems);
+            failed_paragraphs.append(&mut bibliography_failures);
+        }
+        let req_end = Instant::now();
+
+        metadata.dropped_matches.duplicates = dedup_problems(&mut problems);
+        self.apply_severity_overrides(&mut problems);
+
+        let text = paragraphs
+            .iter()
+            .map(|p| p.get_interpreted_text())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let word_count = count_words_naive(&text);
+
+        let mut metadata = Metadata {
+            word_count,
+            languagetool_request_time: req_end.duration_since(req_start),
+            paragraph_count: paragraphs.len(),
+            chunk_statistics: ChunkStatistics::new(source_paragraph_count, &paragraphs),
+            problem_counts: ProblemCounts::new(&problems),
+            ..metadata
+        };
+        // Matches are mapped while other requests are still pending
+        metadata.timings.request = metadata
+            .languagetool_request_time
+            .saturating_sub(metadata.timings.mapping);
+
+        if debug {
+            debug_problems(&problems);
+        }
+
+        Ok((problems, failed_paragraphs, metadata))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use typst_syntax::Source;
+
+    use super::CheckOptions;
+    use crate::{
+        spellchecker::{debug::FILE_NAME, test_server::TestServer},
+        SpellcheckConfig,
+    };
+
+    /// Gets when the debug file was last written, if it exists.
+    fn debug_file_modified() -> Option<std::time::SystemTime> {
+        fs::metadata(FILE_NAME)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    #[tokio::test]
+    async fn check_text_locates_non_ascii_text() {
+        let server = TestServer::start(&["teh"]);
+        let spellchecker = server.spellchecker(SpellcheckConfig::default());
+
+        let text = "Ça für teh café.\n\nZweiter Absatz über teh.";
+        let (problems, _, _) = spellchecker
+            .check_text(text, &CheckOptions::new())
+            .await
+            .unwrap();
+
+        let mut located: Vec<_> = problems
+            .iter()
+            .map(|problem| {
+                let range = problem.range;
+                let found = &text[problem.offset..problem.offset + problem.length];
+
+                (
+                    found,
+                    range.start.line,
+                    range.start.column,
+                    range.end.column,
+                )
+            })
+            .collect();
+        located.sort();
+
+        // Offsets are in bytes, while columns are in characters
+        assert_eq!(located, vec![("teh", 1, 8, 11), ("teh", 3, 21, 24)]);
+        assert_eq!(problems.iter().map(|problem| problem.offset).min(), Some(9));
+    }
+
+    #[tokio::test]
+    async fn checks_only_write_debug_file_when_asked() {
+        let server = TestServer::start(&["teh"]);
+        let spellchecker = server.spellchecker(SpellcheckConfig::default());
+        let modified = debug_file_modified();
+
+        let options = CheckOptions::new().with_language("de-DE");
+        spellchecker
+            .check_text("Some teh text.", &options)
+            .await
+            .unwrap();
+        spellchecker
+            .check_source(&Source::detached("Some teh text."), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(debug_file_modified(), modified);
+    }
+
+    #[tokio::test]
+    async fn unmapped_matches_are_unlocated() {
+        // A match that starts in the break between merged paragraphs has no position
+        let server = TestServer::start(&["\r\n\r\nSecond"]);
+        let spellchecker = server.spellchecker(SpellcheckConfig::default());
+
+        let text = "First paragraph.\n\nSecond paragraph.";
+        let (problems, failed_paragraphs, metadata) = spellchecker
+            .check_text(text, &CheckOptions::new())
+            .await
+            .unwrap();
+
+        assert!(failed_paragraphs.is_empty());
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].unlocated);
+        assert_eq!(problems[0].match_string, "\r\n\r\nSecond");
+
+        // The problem is reported at the merged paragraph
+        assert_eq!(problems[0].offset, 0);
+        assert_eq!(problems[0].length, text.len());
+        assert_eq!(metadata.unlocated_count, 1);
+        assert_eq!(metadata.dropped_matches.unmapped, 0);
+    }
+}
@@ -0,0 +1,542 @@
+use std::{ops, time::Instant};
+
+use languagetool_rust::check::{Data, DataAnnotation, Match};
+use typst::{
+    eval::Tracer,
+    layout::{Abs, Frame, FrameItem, Point},
+    syntax::Source,
+    text::TextItem,
+    World,
+};
+
+use crate::{
+    preprocessor::{paragraph::ParagraphKind, paragraph_kind_at},
+    problem::{dedup_problems, Problem},
+    word_count::count_words_naive,
+    SpellcheckConfig, DEFAULT_MIN_CHUNK_LENGTH,
+};
+
+use super::{
+    check_chunks::Chunk,
+    check_file::Error,
+    check_source::CheckOptions,
+    failed_paragraph::FailedParagraph,
+    metadata::{ChunkStatistics, Metadata, ProblemCounts},
+    Spellchecker,
+};
+
+/// Lines further apart than this many times the font size are in separate blocks, as
+/// are text items on the same line that are this far apart, like table columns.
+const BLOCK_GAP: f64 = 2.0;
+
+/// A block of text in the laid out document.
+#[derive(Debug, Default)]
+struct Block {
+    text: String,
+    /// The glyph clusters of the text, with their byte range in the text and their byte
+    /// offset in the main source, if they come from it.
+    clusters: Vec<(ops::Range<usize>, Option<usize>)>,
+    /// The kind of paragraph the block is checked as, and the byte range of the element
+    /// in the main source that makes it that kind.
+    ///
+    /// `None` if none of the block's text comes from the main source.
+    kind: Option<(ParagraphKind, ops::Range<usize>)>,
+}
+
+impl Block {
+    /// Maps a byte range in the block's text to a byte range in the main source.
+    fn source_range(&self, range: ops::Range<usize>) -> Option<ops::Range<usize>> {
+        let start = self.clusters.iter().find_map(|(cluster, source_offset)| {
+            let offset = (*source_offset)?;
+
+            cluster
+                .contains(&range.start)
+                .then(|| offset + range.start - cluster.start)
+        })?;
+        let end = self.clusters.iter().find_map(|(cluster, source_offset)| {
+            let offset = (*source_offset)?;
+
+            (range.end > cluster.start && range.end <= cluster.end)
+                .then(|| offset + range.end - cluster.start)
+        })?;
+
+        (start <= end).then_some(start..end)
+    }
+
+    fn paragraph_kind(&self) -> ParagraphKind {
+        self.kind
+            .as_ref()
+            .map_or(ParagraphKind::Body, |(kind, _)| *kind)
+    }
+
+    /// Appends another block, separated by a blank line.
+    fn append(&mut self, other: Block) {
+        if !self.text.is_empty() {
+            self.text.push_str("\n\n");
+        }
+
+        let offset = self.text.len();
+        self.text.push_str(&other.text);
+        self.clusters
+            .extend(other.clusters.into_iter().map(|(range, source_offset)| {
+                (offset + range.start..offset + range.end, source_offset)
+            }));
+        self.kind = self.kind.take().or(other.kind);
+    }
+}
+
+/// A chunk of blocks of the laid out document that is checked in a single request.
+struct WorldChunk<'a> {
+    source: &'a Source,
+    block: &'a Block,
+}
+
+impl Chunk for WorldChunk<'_> {
+    fn data(&self) -> Data {
+        [DataAnnotation::new_text(self.block.text.clone())]
+            .into_iter()
+            .collect()
+    }
+
+    fn kind(&self) -> ParagraphKind {
+        self.block.paragraph_kind()
+    }
+
+    fn text(&self) -> &str {
+        &self.block.text
+    }
+
+    fn source(&self) -> &Source {
+        self.source
+    }
+
+    /// Text from templates and other files can't be reported, so matches in it aren't
+    /// located.
+    fn problem(
+        &self,
+        lt_match: Match,
+        range: ops::Range<usize>,
+        match_text: String,
+    ) -> Option<Problem> {
+        let source_range = self.block.source_range(range)?;
+
+        Problem::try_from_byte_range(self.source, lt_match, source_range, match_text)
+    }
+
+    /// Gets the byte range in the main source from the first to the last of the
+    /// block's clusters that come from it.
+    fn source_range(&self) -> Option<ops::Range<usize>> {
+        let mut ranges = self
+            .block
+            .clusters
+            .iter()
+            .filter_map(|(cluster, source_offset)| {
+                let offset = (*source_offset)?;
+
+                Some(offset..offset + cluster.len())
+            });
+
+        let first = ranges.next()?;
+        let (start, end) = ranges.fold((first.start, first.end), |(start, end), range| {
+            (start.min(range.start), end.max(range.end))
+        });
+
+        Some(start..end)
+    }
+}
+
+/// Extracts blocks of text from the frames of a laid out document.
+///
+/// Groups in frames don't say whether their content is inline or a block, so blocks are
+/// separated by the gaps between lines and by the elements of the main source that the
+/// text comes from.
+struct Extractor<'a> {
+    source: &'a Source,
+    config: &'a SpellcheckConfig,
+    blocks: Vec<Block>,
+    current: Block,
+    /// The vertical position, font size and horizontal end of the latest text item.
+    latest_line: Option<(Abs, Abs, Abs)>,
+}
+
+impl<'a> Extractor<'a> {
+    fn new(source: &'a Source, config: &'a SpellcheckConfig) -> Self {
+        Self {
+            source,
+            config,
+            blocks: vec![],
+            current: Block::default(),
+            latest_line: None,
+        }
+    }
+
+    fn finish_block(&mut self) {
+        let block = std::mem::take(&mut self.current);
+        if !block.text.trim().is_empty() {
+            self.blocks.push(block);
+        }
+        self.latest_line = None;
+    }
+
+    /// Extracts the text of a frame, where `origin` is the position of the frame.
+    fn frame(&mut self, frame: &Frame, origin: Point) {
+        for (position, item) in frame.items() {
+            let position = origin + *position;
+
+            match item {
+                FrameItem::Group(group) => self.frame(&group.frame, position),
+                FrameItem::Text(text) => self.text(text, position),
+                _ => {}
+            }
+        }
+    }
+
+    fn text(&mut self, item: &TextItem, position: Point) {
+        // Generated text like list markers and numbering doesn't belong to any element
+        if item.glyphs.iter().all(|glyph| glyph.span.0.is_detached()) {
+            return;
+        }
+
+        let source_offsets: Vec<_> = item
+            .glyphs
+            .iter()
+            .map(|glyph| {
+                let (span, span_offset) = glyph.span;
+
+                (span.id() == Some(self.source.id()))
+                    .then(|| self.source.range(span))
+                    .flatten()
+                    .map(|range| range.start + usize::from(span_offset))
+            })
+            .collect();
+
+        // Text from another element of the source starts a new block
+        let kind = source_offsets
+            .iter()
+            .find_map(|offset| *offset)
+            .map(|offset| paragraph_kind_at(self.source.root(), offset, self.config));
+        if kind.is_some() && self.current.kind.is_some() && kind != self.current.kind {
+            self.finish_block();
+        }
+
+        if let Some((latest_y, size, latest_end)) = self.latest_line {
+            if (position.y - latest_y).abs() > size * BLOCK_GAP
+                || (position.y == latest_y && position.x - latest_end > size * BLOCK_GAP)
+            {
+                self.finish_block();
+            } else if position.y != latest_y && !self.current.text.ends_with(char::is_whitespace) {
+                // Lines of the same paragraph
+                self.current.text.push(' ');
+            }
+        }
+        self.latest_line = Some((position.y, item.size, position.x + item.width()));
+        if self.current.kind.is_none() {
+            self.current.kind = kind;
+        }
+
+        for (glyph, source_offset) in item.glyphs.iter().zip(source_offsets) {
+            let Some(text) = item.text.get(glyph.range()) else {
+                continue;
+            };
+
+            let start = self.current.text.len();
+            self.current.text.push_str(text);
+            self.current
+                .clusters
+                .push((start..self.current.text.len(), source_offset));
+        }
+    }
+}
+
+impl Spellchecker {
+    /// Compiles the main source of a typst `World` and checks the text of the laid out
+    /// document.
+    ///
+    /// Unlike [`Spellchecker::check_source`], this sees text produced by functions,
+    /// templates and imports, and skips content that isn't shown. Only problems in text
+    /// that comes from the main source are reported.
+    ///
+    /// The world has to be `Sync` for the returned future to be `Send`, e.g. so it can be
+    /// spawned on a multithreaded runtime.
+    pub async fn check_world(
+        &self,
+        world: &(dyn World + Sync),
+        options: &CheckOptions,
+    ) -> Result<(Vec<Problem>, Vec<FailedParagraph>, Metadata), Error> {
+        let source = world.main();
+        let mut metadata = Metadata::default();
+
+        // Parsing happens during compilation, so it isn't timed separately
+        let compile_start = Instant::now();
+        let mut tracer = Tracer::new();
+        let document = typst::compile(world, &mut tracer).map_err(|diagnostics| {
+            let messages: Vec<_> = diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.message.to_string())
+                .collect();
+
+            Error::Compile(messages.join("\n"))
+        })?;
+
+        let mut extractor = Extractor::new(&source, &self.spellcheck_config);
+        for page in document.pages.iter() {
+            extractor.frame(&page.frame, Point::zero());
+            extractor.finish_block();
+        }
+        let blocks = extractor.blocks;
+        metadata.timings.preprocess = compile_start.elapsed();
+        let source_paragraph_count = blocks.len();
+
+        // Short blocks are merged into chunks with the same rules, like paragraphs of the
+        // source
+        let chunking = &self.spellcheck_config.chunking;
+        let min_length = chunking.min_length.unwrap_or(DEFAULT_MIN_CHUNK_LENGTH);
+        let max_length = self.max_chunk_length();
+
+        let mut chunks: Vec<Block> = vec![];
+        for block in blocks {
+            match chunks.last_mut() {
+                Some(chunk)
+                    if chunk.paragraph_kind().shares_rules(block.paragraph_kind())
+                        && chunk.text.len() < min_length
+                        && chunk.text.len() + block.text.len() + 2 <= max_length =>
+                {
+                    chunk.append(block)
+                }
+                _ => chunks.push(block),
+            }
+        }
+
+        let world_chunks: Vec<_> = chunks
+            .iter()
+            .map(|block| WorldChunk {
+                source: &source,
+                block,
+            })
+            .collect();
+
+        let req_start = Instant::now();
+        let (mut problems, failed_paragraphs) = self
+            .check_chunks(&world_chunks, options, &mut metadata)
+            .await;
+        let req_end = Instant::now();
+
+        metadata.dropped_matches.duplicates = dedup_problems(&mut problems);
+        self.apply_severity_overrides(&mut problems);
+
+        let text = chunks
+            .iter()
+            .map(|chunk| chunk.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let lengths: Vec<usize> = chunks.iter().map(|chunk| chunk.text.len()).collect();
+
+        let mut metadata = Metadata {
+            word_count: count_words_naive(&text),
+            languagetool_request_time: req_end.duration_since(req_start),
+            paragraph_count: chunks.len(),
+            chunk_statistics: ChunkStatistics::from_lengths(source_paragraph_count, &lengths),
+            problem_counts: ProblemCounts::new(&problems),
+            ..metadata
+        };
+        metadata.timings.request = metadata
+            .languagetool_request_time
+            .saturating_sub(metadata.timings.mapping);
+
+        Ok((problems, failed_paragraphs, metadata))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use comemo::Prehashed;
+    use typst::{
+        diag::{FileError, FileResult},
+        eval::Tracer,
+        foundations::{Bytes, Datetime},
+        layout::Point,
+        syntax::{FileId, Source},
+        text::{Font, FontBook},
+        Library, World,
+    };
+
+    use super::{Block, Extractor};
+    use crate::{
+        preprocessor::paragraph::ParagraphKind,
+        spellchecker::{check_source::CheckOptions, Spellchecker},
+        SpellcheckConfig,
+    };
+
+    /// A world with only a main source and the fonts bundled with typst.
+    struct TestWorld {
+        main: Source,
+        library: Prehashed<Library>,
+        book: Prehashed<FontBook>,
+        fonts: Vec<Font>,
+    }
+
+    impl TestWorld {
+        fn new(text: &str) -> Self {
+            let fonts: Vec<_> = typst_assets::fonts()
+                .flat_map(|data| Font::iter(Bytes::from_static(data)))
+                .collect();
+
+            Self {
+                main: Source::detached(text),
+                library: Prehashed::new(Library::default()),
+                book: Prehashed::new(FontBook::from_fonts(&fonts)),
+                fonts,
+            }
+        }
+    }
+
+    impl World for TestWorld {
+        fn library(&self) -> &Prehashed<Library> {
+            &self.library
+        }
+
+        fn book(&self) -> &Prehashed<FontBook> {
+            &self.book
+        }
+
+        fn main(&self) -> Source {
+            self.main.clone()
+        }
+
+        fn source(&self, id: FileId) -> FileResult<Source> {
+            if id == self.main.id() {
+                Ok(self.main.clone())
+            } else {
+                Err(FileError::AccessDenied)
+            }
+        }
+
+        fn file(&self, _id: FileId) -> FileResult<Bytes> {
+            Err(FileError::AccessDenied)
+        }
+
+        fn font(&self, index: usize) -> Option<Font> {
+            self.fonts.get(index).cloned()
+        }
+
+        fn today(&self, _offset: Option<i64>) -> Option<Datetime> {
+            None
+        }
+    }
+
+    /// Gets the blocks of the laid out document of `text`.
+    fn extract(text: &str) -> (Source, Vec<Block>) {
+        let world = TestWorld::new(text);
+        let document = typst::compile(&world, &mut Tracer::new()).unwrap();
+
+        let source = world.main();
+        let config = SpellcheckConfig::default();
+        let mut extractor = Extractor::new(&source, &config);
+        for page in document.pages.iter() {
+            extractor.frame(&page.frame, Point::zero());
+            extractor.finish_block();
+        }
+
+        let blocks = extractor.blocks;
+        (source, blocks)
+    }
+
+    fn texts(blocks: &[Block]) -> Vec<&str> {
+        blocks.iter().map(|block| block.text.as_str()).collect()
+    }
+
+    #[test]
+    fn source_range() {
+        let block = Block {
+            text: "Hello big world".to_string(),
+            clusters: vec![(0..6, Some(10)), (6..10, None), (10..15, Some(30))],
+            kind: None,
+        };
+
+        assert_eq!(block.source_range(0..5), Some(10..15));
+        assert_eq!(block.source_range(1..6), Some(11..16));
+        assert_eq!(block.source_range(10..15), Some(30..35));
+        // Ranges that start or end in text from outside of the main source can't be mapped
+        assert_eq!(block.source_range(6..9), None);
+        assert_eq!(block.source_range(0..8), None);
+        assert_eq!(block.source_range(20..22), None);
+    }
+
+    #[test]
+    fn inline_elements_stay_in_the_sentence() {
+        let (_, blocks) = extract(
+            "Some #box[boxed] text with a #link(\"https://typst.app\")[link] and \
+             #highlight[marked] words.",
+        );
+
+        assert_eq!(
+            texts(&blocks),
+            ["Some boxed text with a link and marked words."]
+        );
+    }
+
+    #[test]
+    fn blocks_have_the_kind_of_their_element() {
+        let (_, blocks) = extract("= Heading\n\nSome text.\n\n- First item\n- Second item");
+
+        let kinds: Vec<_> = blocks.iter().map(Block::paragraph_kind).collect();
+
+        assert_eq!(
+            texts(&blocks),
+            ["Heading", "Some text.", "First item", "Second item"]
+        );
+        assert_eq!(
+            kinds,
+            [
+                ParagraphKind::Heading { level: 1 },
+                ParagraphKind::Body,
+                ParagraphKind::ListItem,
+                ParagraphKind::ListItem,
+            ]
+        );
+    }
+
+    #[test]
+    fn footnotes_are_separate() {
+        let (_, blocks) = extract("Some text#footnote[A note.] continues here.");
+
+        let kinds: Vec<_> = blocks.iter().map(Block::paragraph_kind).collect();
+
+        assert_eq!(texts(&blocks), ["Some text continues here.", "A note."]);
+        assert_eq!(kinds, [ParagraphKind::Body, ParagraphKind::Footnote]);
+    }
+
+    #[test]
+    fn table_cells_are_separate() {
+        let (_, blocks) = extract("#table(columns: 2, [First cell], [Second cell])");
+
+        assert_eq!(texts(&blocks), ["First cell", "Second cell"]);
+        assert!(blocks
+            .iter()
+            .all(|block| block.paragraph_kind() == ParagraphKind::TableCell));
+    }
+
+    #[test]
+    fn blocks_map_to_source() {
+        let text = "= Intro\n\nA #emph[mispeled] word.";
+        let (_, blocks) = extract(text);
+
+        let block = &blocks[1];
+        let start = block.text.find("mispeled").unwrap();
+        let range = block.source_range(start..start + "mispeled".len()).unwrap();
+
+        assert_eq!(&text[range], "mispeled");
+    }
+
+    #[test]
+    fn check_world_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+
+        let world = TestWorld::new("Some text.");
+        let spellchecker = Spellchecker::new(Default::default(), SpellcheckConfig::default());
+        let options = CheckOptions::new();
+
+        // The future is only created to check its type, and is never polled
+        assert_send(&spellchecker.check_world(&world, &options));
+    }
+}
@@ -11,7 +11,7 @@ use crate::{
     problem::Problem,
 };
 
-const FILE_NAME: &str = "typst-spellcheck.debug.log";
+pub(super) const FILE_NAME: &str = "typst-spellcheck.debug.log";
 
 pub fn setup_debug_file() {
     let _file = File::create(FILE_NAME).unwrap();
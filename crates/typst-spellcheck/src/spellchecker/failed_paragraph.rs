@@ -0,0 +1,12 @@
+use crate::range::Range;
+
+use super::check_file::Error;
+
+/// A paragraph that could not be checked by languagetool.
+#[derive(Debug)]
+pub struct FailedParagraph {
+    /// The file the paragraph is in, if it isn't the checked typst file.
+    pub file: Option<String>,
+    pub range: Option<Range>,
+    pub error: Error,
+}
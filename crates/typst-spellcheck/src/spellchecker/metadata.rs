@@ -1,7 +1,212 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
+use crate::{preprocessor::paragraph::Paragraph, problem::Problem, severity::Severity};
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Metadata {
     pub word_count: usize,
     pub paragraph_count: usize,
     pub languagetool_request_time: Duration,
+    pub chunk_statistics: ChunkStatistics,
+    pub timings: PhaseTimings,
+    pub problem_counts: ProblemCounts,
+    pub dropped_matches: DroppedMatches,
+    pub request_statistics: RequestStatistics,
+    /// Number of problems that couldn't be mapped to their exact position.
+    pub unlocated_count: usize,
+}
+
+/// How long each phase of a check took.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhaseTimings {
+    /// Parsing the source, which is zero if it was parsed before the check.
+    pub parse: Duration,
+    /// Building, splitting and merging the paragraphs.
+    pub preprocess: Duration,
+    /// Waiting for languagetool responses.
+    pub request: Duration,
+    /// Turning languagetool matches into problems.
+    pub mapping: Duration,
+}
+
+/// Number of problems per severity, rule category and rule id.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProblemCounts {
+    pub by_severity: BTreeMap<Severity, usize>,
+    pub by_category: BTreeMap<String, usize>,
+    pub by_rule: BTreeMap<String, usize>,
+}
+
+impl ProblemCounts {
+    pub fn new(problems: &[Problem]) -> Self {
+        let mut counts = Self::default();
+
+        for problem in problems {
+            *counts.by_severity.entry(problem.severity).or_default() += 1;
+            *counts
+                .by_category
+                .entry(problem.rule_category.clone())
+                .or_default() += 1;
+            *counts.by_rule.entry(problem.rule_id.clone()).or_default() += 1;
+        }
+
+        counts
+    }
+}
+
+/// Number of languagetool matches that weren't reported as problems.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DroppedMatches {
+    /// Matches of an ignored word.
+    pub ignored_words: usize,
+    /// Matches that couldn't be mapped back to the source.
+    pub unmapped: usize,
+    /// Matches with the same range and rule as another match, e.g. from overlapping
+    /// chunks.
+    pub duplicates: usize,
+}
+
+impl DroppedMatches {
+    pub fn total(&self) -> usize {
+        self.ignored_words + self.unmapped + self.duplicates
+    }
+}
+
+/// Statistics about the requests sent to languagetool.
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RequestStatistics {
+    pub request_count: usize,
+    /// Sum of the time each request took until its response arrived.
+    pub total_latency: Duration,
+}
+
+impl RequestStatistics {
+    /// Records a request that took `latency`.
+    pub fn record(&mut self, latency: Duration) {
+        self.request_count += 1;
+        self.total_latency += latency;
+    }
+
+    pub fn average_latency(&self) -> Duration {
+        self.total_latency
+            .checked_div(self.request_count as u32)
+            .unwrap_or_default()
+    }
+}
+
+/// Statistics about the chunks of text sent to languagetool.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkStatistics {
+    /// Number of paragraphs found in the document before merging and splitting.
+    pub source_paragraph_count: usize,
+    pub chunk_count: usize,
+    /// Length of the shortest chunk in bytes.
+    pub min_length: usize,
+    /// Length of the longest chunk in bytes.
+    pub max_length: usize,
+    /// Mean length of the chunks in bytes.
+    pub mean_length: usize,
+}
+
+impl ChunkStatistics {
+    pub fn new(source_paragraph_count: usize, chunks: &[Paragraph]) -> Self {
+        let lengths: Vec<usize> = chunks.iter().map(|c| c.get_text().0.len()).collect();
+
+        Self::from_lengths(source_paragraph_count, &lengths)
+    }
+
+    /// Creates the statistics from the length of each chunk in bytes.
+    pub fn from_lengths(source_paragraph_count: usize, lengths: &[usize]) -> Self {
+        if lengths.is_empty() {
+            return Self {
+                source_paragraph_count,
+                ..Default::default()
+            };
+        }
+
+        Self {
+            source_paragraph_count,
+            chunk_count: lengths.len(),
+            min_length: *lengths.iter().min().unwrap(),
+            max_length: *lengths.iter().max().unwrap(),
+            mean_length: lengths.iter().sum::<usize>() / lengths.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst_syntax::Source;
+
+    use super::{ChunkStatistics, ProblemCounts};
+    use crate::{preprocessor::preprocess, problem::Problem, severity::Severity, SpellcheckConfig};
+
+    fn problem(rule_id: &str, rule_category: &str, severity: Severity) -> Problem {
+        Problem {
+            rule_id: rule_id.to_string(),
+            rule_category: rule_category.to_string(),
+            severity,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn chunk_statistics_of_paragraphs() {
+        let source = Source::detached("One.\n\nTwo words.\n\nThree short words.");
+        let paragraphs = preprocess(source.root(), &SpellcheckConfig::default());
+
+        let statistics = ChunkStatistics::new(5, &paragraphs);
+        assert_eq!(statistics.source_paragraph_count, 5);
+        assert_eq!(statistics.chunk_count, 3);
+        assert_eq!(statistics.min_length, 4);
+        assert_eq!(statistics.max_length, 18);
+        assert_eq!(statistics.mean_length, 10);
+    }
+
+    #[test]
+    fn chunk_statistics_without_chunks() {
+        let statistics = ChunkStatistics::new(2, &[]);
+
+        assert_eq!(statistics.source_paragraph_count, 2);
+        assert_eq!(statistics.chunk_count, 0);
+        assert_eq!(statistics.mean_length, 0);
+    }
+
+    #[test]
+    fn problem_counts() {
+        let problems = [
+            problem("MORFOLOGIK_RULE_EN_US", "TYPOS", Severity::Error),
+            problem("MORFOLOGIK_RULE_EN_US", "TYPOS", Severity::Error),
+            problem("EN_A_VS_AN", "MISC", Severity::Warning),
+            problem("PASSIVE_VOICE", "STYLE", Severity::Hint),
+        ];
+        let counts = ProblemCounts::new(&problems);
+
+        assert_eq!(
+            counts.by_severity.into_iter().collect::<Vec<_>>(),
+            vec![
+                (Severity::Hint, 1),
+                (Severity::Warning, 1),
+                (Severity::Error, 2)
+            ]
+        );
+        assert_eq!(
+            counts.by_category.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("MISC".to_string(), 1),
+                ("STYLE".to_string(), 1),
+                ("TYPOS".to_string(), 2)
+            ]
+        );
+        assert_eq!(counts.by_rule.len(), 3);
+        assert_eq!(counts.by_rule["MORFOLOGIK_RULE_EN_US"], 2);
+
+        assert!(ProblemCounts::new(&[]).by_rule.is_empty());
+    }
 }
@@ -1,12 +1,27 @@
+pub mod check_bibliography;
+mod check_chunks;
 pub mod check_file;
+pub mod check_source;
+#[cfg(feature = "world")]
+pub mod check_world;
 pub mod debug;
+pub mod failed_paragraph;
 pub mod metadata;
+#[cfg(test)]
+mod test_server;
 
-use std::sync::Arc;
+use std::{ops, sync::Arc};
 
-use languagetool_rust::ServerClient;
+use languagetool_rust::{
+    check::{Data, Level},
+    CheckRequest, ServerClient,
+};
 
-use crate::{LanguageToolConfig, SpellcheckConfig};
+use check_source::CheckOptions;
+
+use crate::{
+    preprocessor::paragraph::ParagraphKind, problem::Problem, LanguageToolConfig, SpellcheckConfig,
+};
 
 pub struct Spellchecker {
     pub languagetool_config: LanguageToolConfig,
@@ -19,13 +34,14 @@ impl Spellchecker {
         languagetool_config: LanguageToolConfig,
         spellcheck_config: SpellcheckConfig,
     ) -> Self {
-        let client = Arc::new(
-            ServerClient::new(
-                &languagetool_config.host,
-                &languagetool_config.port.to_string(),
-            )
-            .with_max_suggestions(5),
-        );
+        let mut client = ServerClient::new(
+            &languagetool_config.host,
+            &languagetool_config.port.to_string(),
+        )
+        .with_max_suggestions(5);
+        client.api = languagetool_config.api_url();
+
+        let client = Arc::new(client);
 
         Self {
             languagetool_config,
@@ -33,4 +49,90 @@ impl Spellchecker {
             client,
         }
     }
+
+    /// Creates a check request for some annotated text using the languagetool config,
+    /// with the rules for the kind of paragraph it is and the options for the check.
+    fn request(&self, data: Data, kind: ParagraphKind, options: &CheckOptions) -> CheckRequest {
+        let language = options
+            .language
+            .clone()
+            .unwrap_or_else(|| self.languagetool_config.language.clone());
+        let mut request = CheckRequest::default()
+            .with_data(data)
+            .with_language(language);
+
+        if self.languagetool_config.picky.unwrap_or(false) {
+            request.level = Level::Picky
+        }
+
+        let (disabled_rules, disabled_categories) = self.spellcheck_config.disabled_for(kind);
+        request.disabled_rules = extend(&self.languagetool_config.disabled_rules, disabled_rules);
+        request.disabled_categories = extend(
+            &self.languagetool_config.disabled_categories,
+            disabled_categories,
+        );
+        request
+            .username
+            .clone_from(&self.languagetool_config.username);
+        request
+            .api_key
+            .clone_from(&self.languagetool_config.api_key);
+
+        request
+    }
+
+    /// Maximum number of bytes in a chunk, which is the configured maximum or the text
+    /// limit of the languagetool API, whichever is smaller.
+    fn max_chunk_length(&self) -> usize {
+        let configured = self.spellcheck_config.chunking.max_length;
+        let limit = self.languagetool_config.max_text_length();
+
+        configured
+            .unwrap_or(usize::MAX)
+            .min(limit.unwrap_or(usize::MAX))
+    }
+
+    /// Replaces the derived severity of problems with the configured overrides.
+    fn apply_severity_overrides(&self, problems: &mut [Problem]) {
+        let overrides = &self.spellcheck_config.severity;
+
+        for problem in problems.iter_mut() {
+            problem.severity =
+                overrides.resolve(&problem.rule_id, &problem.rule_category, problem.severity);
+        }
+    }
+}
+
+/// Adds `extra` to a list of rules or categories.
+fn extend(list: &Option<Vec<String>>, extra: Vec<String>) -> Option<Vec<String>> {
+    if extra.is_empty() {
+        return list.clone();
+    }
+
+    let mut list = list.clone().unwrap_or_default();
+    list.extend(extra);
+    Some(list)
+}
+
+/// Converts a range in UTF-16 code units, as used by languagetool, into a byte range in `text`.
+pub(crate) fn utf16_to_byte_range(
+    text: &str,
+    offset: usize,
+    length: usize,
+) -> Option<ops::Range<usize>> {
+    let mut utf16_index = 0;
+    let mut start = None;
+
+    for (byte_index, char) in text.char_indices().chain([(text.len(), '\0')]) {
+        if utf16_index == offset {
+            start = Some(byte_index);
+        }
+        if utf16_index == offset + length {
+            return Some(start?..byte_index);
+        }
+
+        utf16_index += char.len_utf16();
+    }
+
+    None
 }
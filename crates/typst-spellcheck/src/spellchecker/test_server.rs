@@ -0,0 +1,181 @@
+//! A minimal languagetool server for tests.
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use serde_json::{json, Value};
+
+use crate::{LanguageToolConfig, SpellcheckConfig};
+
+use super::Spellchecker;
+
+/// Requests with this in their text fail with a server error.
+pub const FAILURE: &str = "FAIL";
+
+/// A languagetool server that reports each occurrence of its flagged strings in the text
+/// of a request, including markup, as a match of `TEST_RULE`.
+pub struct TestServer {
+    port: u16,
+}
+
+impl TestServer {
+    pub fn start(flagged: &'static [&'static str]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                thread::spawn(move || handle(stream, flagged));
+            }
+        });
+
+        Self { port }
+    }
+
+    /// Creates a spellchecker that sends its requests to this server.
+    pub fn spellchecker(&self, spellcheck_config: SpellcheckConfig) -> Spellchecker {
+        let languagetool_config = LanguageToolConfig {
+            host: "http://127.0.0.1".to_string(),
+            port: self.port,
+            language: "en-US".to_string(),
+            ..Default::default()
+        };
+
+        Spellchecker::new(languagetool_config, spellcheck_config)
+    }
+}
+
+/// Answers the requests of a connection until it is closed.
+fn handle(stream: TcpStream, flagged: &[&str]) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+
+    loop {
+        let mut request_line = String::new();
+        if reader.read_line(&mut request_line).unwrap_or(0) == 0 {
+            return;
+        }
+
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            if header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        reader.read_exact(&mut vec![0; content_length]).unwrap();
+
+        let path = request_line.split(' ').nth(1).unwrap_or_default();
+        let text = request_text(path);
+
+        let (status, body) = if text.contains(FAILURE) {
+            ("500 Internal Server Error", "Failed".to_string())
+        } else {
+            ("200 OK", response(&text, flagged).to_string())
+        };
+
+        let response = format!(
+            "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        if stream.write_all(response.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Gets the text of a request's annotated data, including markup.
+fn request_text(path: &str) -> String {
+    let query = path.split_once('?').map_or("", |(_, query)| query);
+    let Some(data) = query.split('&').find_map(|pair| pair.strip_prefix("data=")) else {
+        return String::new();
+    };
+
+    let data: Value = serde_json::from_str(&percent_decode(data)).unwrap();
+    data["annotation"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|annotation| {
+            annotation["text"]
+                .as_str()
+                .or(annotation["markup"].as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let mut bytes = vec![];
+    let mut input = text.bytes();
+
+    while let Some(byte) = input.next() {
+        match byte {
+            b'+' => bytes.push(b' '),
+            b'%' => {
+                let hex = [input.next().unwrap(), input.next().unwrap()];
+                let hex = std::str::from_utf8(&hex).unwrap();
+                bytes.push(u8::from_str_radix(hex, 16).unwrap());
+            }
+            _ => bytes.push(byte),
+        }
+    }
+
+    String::from_utf8(bytes).unwrap()
+}
+
+/// Builds a check response with a match for each occurrence of the flagged strings.
+fn response(text: &str, flagged: &[&str]) -> Value {
+    let utf16_length = |text: &str| text.encode_utf16().count();
+
+    let matches: Vec<Value> = flagged
+        .iter()
+        .flat_map(|flagged| text.match_indices(flagged))
+        .map(|(offset, flagged)| {
+            json!({
+                "context": { "text": flagged, "offset": 0, "length": utf16_length(flagged) },
+                "contextForSureMatch": 0,
+                "ignoreForIncompleteSentence": false,
+                "offset": utf16_length(&text[..offset]),
+                "length": utf16_length(flagged),
+                "message": "Flagged by the test server.",
+                "shortMessage": "Flagged",
+                "replacements": [],
+                "rule": {
+                    "id": "TEST_RULE",
+                    "description": "Test rule",
+                    "issueType": "misspelling",
+                    "category": { "id": "TYPOS", "name": "Possible Typo" }
+                },
+                "sentence": text,
+                "type": { "typeName": "UnknownWord" }
+            })
+        })
+        .collect();
+
+    json!({
+        "language": {
+            "code": "en-US",
+            "name": "English (US)",
+            "detectedLanguage": { "code": "en-US", "name": "English (US)" }
+        },
+        "matches": matches,
+        "software": {
+            "apiVersion": 1,
+            "buildDate": "",
+            "name": "LanguageTool",
+            "premium": false,
+            "status": "",
+            "version": "6.0"
+        }
+    })
+}
@@ -17,7 +17,7 @@ pub fn count_words_naive(text: &str) -> usize {
         in_word = true;
     }
 
-    if in_word == true {
+    if in_word {
         word_count += 1;
     }
 
@@ -0,0 +1,125 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use comemo::Prehashed;
+use typst::{
+    diag::{FileError, FileResult, PackageError},
+    foundations::{Bytes, Datetime},
+    syntax::{FileId, Source},
+    text::{Font, FontBook},
+    Library, World,
+};
+
+/// A typst `World` that reads files from a local root directory and fonts from a local
+/// font directory.
+///
+/// Packages aren't downloaded, so documents that import packages fail to compile.
+pub struct LocalWorld {
+    root: PathBuf,
+    main: Source,
+    library: Prehashed<Library>,
+    book: Prehashed<FontBook>,
+    fonts: Vec<Font>,
+}
+
+impl LocalWorld {
+    /// Creates a world for the `main` source, with files relative to `root` and the fonts
+    /// found in `font_dir` and its subdirectories.
+    pub fn new(root: impl AsRef<Path>, main: Source, font_dir: Option<&Path>) -> Self {
+        let mut fonts = vec![];
+        if let Some(font_dir) = font_dir {
+            load_fonts(font_dir, &mut fonts);
+        }
+
+        Self {
+            root: root.as_ref().to_path_buf(),
+            main,
+            library: Prehashed::new(Library::default()),
+            book: Prehashed::new(FontBook::from_fonts(&fonts)),
+            fonts,
+        }
+    }
+
+    fn read(&self, id: FileId) -> FileResult<Vec<u8>> {
+        if let Some(package) = id.package() {
+            return Err(FileError::Package(PackageError::NotFound(package.clone())));
+        }
+
+        let path = id
+            .vpath()
+            .resolve(&self.root)
+            .ok_or(FileError::AccessDenied)?;
+
+        fs::read(&path).map_err(|error| FileError::from_io(error, &path))
+    }
+}
+
+impl World for LocalWorld {
+    fn library(&self) -> &Prehashed<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &Prehashed<FontBook> {
+        &self.book
+    }
+
+    fn main(&self) -> Source {
+        self.main.clone()
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        if id == self.main.id() {
+            return Ok(self.main.clone());
+        }
+
+        let text = String::from_utf8(self.read(id)?).map_err(|_| FileError::InvalidUtf8)?;
+
+        Ok(Source::new(id, text))
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.read(id).map(Bytes::from)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.fonts.get(index).cloned()
+    }
+
+    fn today(&self, _offset: Option<i64>) -> Option<Datetime> {
+        None
+    }
+}
+
+/// Loads the fonts in a directory and its subdirectories.
+fn load_fonts(dir: &Path, fonts: &mut Vec<Font>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        log::warn!("Failed to read font directory: {}", dir.display());
+        return;
+    };
+
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if path.is_dir() {
+            load_fonts(&path, fonts);
+            continue;
+        }
+
+        let is_font = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                matches!(
+                    extension.to_ascii_lowercase().as_str(),
+                    "ttf" | "otf" | "ttc" | "otc"
+                )
+            });
+
+        if is_font {
+            match fs::read(&path) {
+                Ok(data) => fonts.extend(Font::iter(Bytes::from(data))),
+                Err(error) => log::warn!("Failed to read font {}: {}", path.display(), error),
+            }
+        }
+    }
+}